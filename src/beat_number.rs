@@ -1,27 +1,60 @@
+pub const TICKS_PER_QUARTER_NOTE: u32 = 24;
+pub const TICKS_PER_SIXTEENTH_NOTE: u32 = TICKS_PER_QUARTER_NOTE / 4;
+
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct BeatNumber {
     pub sixteenth_note: u32,
+    pub tick: u32,
+    pub sixteenths_per_measure: u32,
 }
 
 impl BeatNumber {
+    pub fn new(sixteenth_note: u32, sixteenths_per_measure: u32) -> Self {
+        Self {
+            sixteenth_note,
+            tick: 0,
+            sixteenths_per_measure,
+        }
+    }
+
+    pub fn from_ticks(ticks_this_measure: u32, sixteenths_per_measure: u32) -> Self {
+        Self {
+            sixteenth_note: ticks_this_measure / TICKS_PER_SIXTEENTH_NOTE,
+            tick: ticks_this_measure % TICKS_PER_SIXTEENTH_NOTE,
+            sixteenths_per_measure,
+        }
+    }
+
+    pub fn with_tick(self, tick: u32) -> Self {
+        Self { tick, ..self }
+    }
+
     pub fn is_beginning_of_measure(&self) -> bool {
-        self.sixteenth_note == 0
+        self.sixteenth_note == 0 && self.is_on_sixteenth_note()
+    }
+
+    pub fn is_on_sixteenth_note(&self) -> bool {
+        self.tick == 0
     }
 
     pub fn minus_sixteenths(&self, num_sixteenths: u32) -> BeatNumber {
         BeatNumber {
-            sixteenth_note: (self.sixteenth_note as i32 - num_sixteenths as i32) as u32 % 16,
+            sixteenth_note: (self.sixteenth_note as i32 - num_sixteenths as i32)
+                .rem_euclid(self.sixteenths_per_measure as i32) as u32,
+            ..*self
         }
     }
 
     pub fn add_sixteenths(&self, num_sixteenths: u32) -> BeatNumber {
         BeatNumber {
-            sixteenth_note: (self.sixteenth_note + num_sixteenths) % 16,
+            sixteenth_note: (self.sixteenth_note + num_sixteenths) % self.sixteenths_per_measure,
+            ..*self
         }
     }
 
     pub fn duration_since(&self, other: &BeatNumber) -> u32 {
-        (self.sixteenth_note + 16 - other.sixteenth_note) % 16
+        (self.sixteenth_note + self.sixteenths_per_measure - other.sixteenth_note)
+            % self.sixteenths_per_measure
     }
 }
 
@@ -32,39 +65,69 @@ mod tests {
     #[test]
     fn minus_sixteenths_same_measure() {
         assert_eq!(
-            BeatNumber { sixteenth_note: 2 }.minus_sixteenths(2),
-            BeatNumber { sixteenth_note: 0 }
+            BeatNumber::new(2, 16).minus_sixteenths(2),
+            BeatNumber::new(0, 16)
         );
     }
 
     #[test]
     fn minus_sixteenths_wrap_around_measure() {
         assert_eq!(
-            BeatNumber { sixteenth_note: 2 }.minus_sixteenths(3),
-            BeatNumber { sixteenth_note: 15 }
+            BeatNumber::new(2, 16).minus_sixteenths(3),
+            BeatNumber::new(15, 16)
+        );
+    }
+
+    #[test]
+    fn minus_sixteenths_wrap_around_three_four_measure() {
+        assert_eq!(
+            BeatNumber::new(2, 12).minus_sixteenths(3),
+            BeatNumber::new(11, 12)
         );
     }
 
     #[test]
     fn add_sixteenths_same_measure() {
         assert_eq!(
-            BeatNumber { sixteenth_note: 1 }.add_sixteenths(2),
-            BeatNumber { sixteenth_note: 3 }
+            BeatNumber::new(1, 16).add_sixteenths(2),
+            BeatNumber::new(3, 16)
         );
     }
 
     #[test]
     fn add_sixteenths_wrap_around_measure() {
         assert_eq!(
-            BeatNumber { sixteenth_note: 14 }.add_sixteenths(2),
-            BeatNumber { sixteenth_note: 0 }
+            BeatNumber::new(14, 16).add_sixteenths(2),
+            BeatNumber::new(0, 16)
+        );
+    }
+
+    #[test]
+    fn add_sixteenths_wrap_around_seven_eight_measure() {
+        assert_eq!(
+            BeatNumber::new(13, 14).add_sixteenths(2),
+            BeatNumber::new(1, 14)
+        );
+    }
+
+    #[test]
+    fn from_ticks() {
+        assert_eq!(
+            BeatNumber::from_ticks(27, 16),
+            BeatNumber {
+                sixteenth_note: 4,
+                tick: 3,
+                sixteenths_per_measure: 16
+            }
         );
+        assert!(BeatNumber::from_ticks(0, 12).is_beginning_of_measure());
+        assert!(!BeatNumber::from_ticks(1, 12).is_beginning_of_measure());
     }
 
     #[test]
     fn duration_since_simple() {
         assert_eq!(
-            BeatNumber { sixteenth_note: 14 }.duration_since(&BeatNumber { sixteenth_note: 2 }),
+            BeatNumber::new(14, 16).duration_since(&BeatNumber::new(2, 16)),
             12
         );
     }
@@ -72,7 +135,7 @@ mod tests {
     #[test]
     fn duration_since_wrap_around() {
         assert_eq!(
-            BeatNumber { sixteenth_note: 2 }.duration_since(&BeatNumber { sixteenth_note: 15 }),
+            BeatNumber::new(2, 16).duration_since(&BeatNumber::new(15, 16)),
             3
         );
     }
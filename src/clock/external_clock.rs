@@ -0,0 +1,50 @@
+use midir::os::unix::VirtualInput;
+use midir::MidiInput;
+use std::time::Instant;
+use wmidi::MidiMessage;
+
+use super::{Clock, ClockGuard};
+use crate::{Message, MidiClockTracker, Result};
+
+pub struct ExternalClock;
+
+impl Clock for ExternalClock {
+    fn start(self: Box<Self>, mut midi_clock_tracker: MidiClockTracker) -> Result<ClockGuard> {
+        let midi_in = MidiInput::new("Line runner")?;
+
+        let connection = midi_in
+            .create_virtual(
+                "Line runner",
+                move |timestamp, bytes, _| {
+                    // Timestamped here rather than by the launcher, so tempo estimates only see
+                    // the jitter of the incoming clock
+                    let at = Instant::now();
+                    if let Some(message) = Message::from(timestamp, bytes).unwrap() {
+                        // Fails only once the launcher has hung up.
+                        handle_message(message, at, &mut midi_clock_tracker).ok();
+                    }
+                },
+                (),
+            )
+            .map_err(|err| anyhow::anyhow!("Could not create virtual MIDI input: {}", err))?;
+
+        Ok(ClockGuard::input(connection))
+    }
+}
+
+fn handle_message(
+    message: Message,
+    at: Instant,
+    midi_clock_tracker: &mut MidiClockTracker,
+) -> Result<()> {
+    match message.message {
+        MidiMessage::TimingClock => midi_clock_tracker.tick_at(at),
+        MidiMessage::Start => midi_clock_tracker.start(),
+        MidiMessage::Stop => midi_clock_tracker.stop(),
+        MidiMessage::Continue => midi_clock_tracker.resume(),
+        MidiMessage::SongPositionPointer(position) => {
+            midi_clock_tracker.set_song_position(u16::from(position).into())
+        }
+        _ => Ok(()),
+    }
+}
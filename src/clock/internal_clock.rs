@@ -0,0 +1,179 @@
+use midir::os::unix::VirtualOutput;
+use midir::{MidiOutput, MidiOutputConnection};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+use wmidi::MidiMessage;
+
+use super::{Clock, ClockGuard};
+use crate::{beat_number::TICKS_PER_QUARTER_NOTE, MidiClockTracker, MidiSink, Result};
+
+pub struct InternalClock {
+    bpm: f64,
+    send_clock: bool,
+}
+
+impl InternalClock {
+    pub fn new(bpm: f64) -> Self {
+        Self {
+            bpm,
+            send_clock: false,
+        }
+    }
+
+    pub fn with_send_clock(self, send_clock: bool) -> Self {
+        Self { send_clock, ..self }
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_secs_f64(60.0 / self.bpm / TICKS_PER_QUARTER_NOTE as f64)
+    }
+}
+
+impl Clock for InternalClock {
+    fn start(self: Box<Self>, midi_clock_tracker: MidiClockTracker) -> Result<ClockGuard> {
+        let clock_output = if self.send_clock {
+            Some(
+                MidiOutput::new("Line runner clock")?
+                    .create_virtual("Line runner clock")
+                    .map_err(|err| {
+                        anyhow::anyhow!("Could not create virtual MIDI clock output: {}", err)
+                    })?,
+            )
+        } else {
+            None
+        };
+        let tick_duration = self.tick_duration();
+
+        let is_running = Arc::new(AtomicBool::new(true));
+
+        eprintln!("Internal clock: {} BPM", self.bpm);
+
+        let thread_is_running = is_running.clone();
+        thread::spawn(move || {
+            run(
+                midi_clock_tracker,
+                clock_output,
+                tick_duration,
+                &thread_is_running,
+                spin_sleep::sleep,
+            )
+        });
+
+        Ok(ClockGuard::thread(is_running))
+    }
+}
+
+fn run(
+    mut midi_clock_tracker: MidiClockTracker,
+    mut clock_output: Option<MidiOutputConnection>,
+    tick_duration: Duration,
+    is_running: &AtomicBool,
+    sleep: impl FnMut(Duration),
+) {
+    // Sending fails once the launcher has hung up, which also ends the clock.
+    tick_while_running(
+        &mut midi_clock_tracker,
+        &mut clock_output,
+        tick_duration,
+        is_running,
+        sleep,
+    )
+    .ok();
+    send(&mut clock_output, MidiMessage::Stop).ok();
+}
+
+fn tick_while_running(
+    midi_clock_tracker: &mut MidiClockTracker,
+    clock_output: &mut Option<MidiOutputConnection>,
+    tick_duration: Duration,
+    is_running: &AtomicBool,
+    mut sleep: impl FnMut(Duration),
+) -> Result<()> {
+    send(clock_output, MidiMessage::Start)?;
+    midi_clock_tracker.start()?;
+
+    let mut next_tick = Instant::now();
+    while is_running.load(Ordering::SeqCst) {
+        send(clock_output, MidiMessage::TimingClock)?;
+        midi_clock_tracker.tick_at(next_tick)?;
+
+        next_tick += tick_duration;
+        sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+
+    Ok(())
+}
+
+fn send(clock_output: &mut Option<MidiOutputConnection>, midi_message: MidiMessage) -> Result<()> {
+    if let Some(clock_output) = clock_output {
+        MidiSink::send(clock_output, &midi_message)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BeatNumber, ClockMessage, Meter};
+
+    #[test]
+    fn tick_duration() {
+        assert_eq!(
+            InternalClock::new(125.0).tick_duration(),
+            Duration::from_millis(20)
+        );
+    }
+
+    #[test]
+    fn drives_the_clock_tracker() {
+        let (midi_clock_tracker, receiver) = MidiClockTracker::new(Meter::new(4, 4));
+        let is_running = AtomicBool::new(true);
+        let mut sleeps = 0;
+
+        run(
+            midi_clock_tracker,
+            None,
+            Duration::from_millis(20),
+            &is_running,
+            |_| {
+                sleeps += 1;
+                if sleeps == 7 {
+                    is_running.store(false, Ordering::SeqCst);
+                }
+            },
+        );
+
+        let messages: Vec<_> = receiver.try_iter().collect();
+
+        assert_eq!(messages.len(), 8);
+        assert_eq!(messages[0], ClockMessage::Start);
+        assert!(
+            matches!(messages[1], ClockMessage::Beat(beat_number, _) if beat_number == BeatNumber::new(0, 16))
+        );
+        assert!(
+            matches!(messages[7], ClockMessage::Beat(beat_number, _) if beat_number == BeatNumber::new(1, 16))
+        );
+    }
+
+    #[test]
+    fn stops_when_receiver_is_dropped() {
+        let (midi_clock_tracker, receiver) = MidiClockTracker::new(Meter::new(4, 4));
+        drop(receiver);
+        let is_running = AtomicBool::new(true);
+        let mut sleeps = 0;
+
+        run(
+            midi_clock_tracker,
+            None,
+            Duration::from_millis(20),
+            &is_running,
+            |_| sleeps += 1,
+        );
+
+        assert_eq!(sleeps, 0);
+    }
+}
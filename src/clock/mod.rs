@@ -0,0 +1,47 @@
+mod external_clock;
+mod internal_clock;
+
+pub use external_clock::ExternalClock;
+pub use internal_clock::InternalClock;
+
+use crate::{MidiClockTracker, Result};
+use midir::MidiInputConnection;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+pub trait Clock {
+    fn start(self: Box<Self>, midi_clock_tracker: MidiClockTracker) -> Result<ClockGuard>;
+}
+
+/// Keeps a started clock running until dropped.
+pub struct ClockGuard(ClockHandle);
+
+enum ClockHandle {
+    Thread(Arc<AtomicBool>),
+    Input(Option<MidiInputConnection<()>>),
+}
+
+impl ClockGuard {
+    fn thread(is_running: Arc<AtomicBool>) -> Self {
+        Self(ClockHandle::Thread(is_running))
+    }
+
+    fn input(connection: MidiInputConnection<()>) -> Self {
+        Self(ClockHandle::Input(Some(connection)))
+    }
+}
+
+impl Drop for ClockGuard {
+    fn drop(&mut self) {
+        match &mut self.0 {
+            ClockHandle::Thread(is_running) => is_running.store(false, Ordering::SeqCst),
+            ClockHandle::Input(connection) => {
+                if let Some(connection) = connection.take() {
+                    connection.close();
+                }
+            }
+        }
+    }
+}
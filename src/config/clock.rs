@@ -0,0 +1,95 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::clock::{Clock as ClockTrait, ExternalClock, InternalClock};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockSource {
+    External,
+    Internal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Clock {
+    #[serde(default = "default_source")]
+    pub source: ClockSource,
+    #[serde(default = "default_bpm", deserialize_with = "deserialize_bpm")]
+    pub bpm: f64,
+    #[serde(default)]
+    pub send_clock: bool,
+}
+
+impl Clock {
+    pub fn to_clock(&self) -> Box<dyn ClockTrait> {
+        match self.source {
+            ClockSource::External => Box::new(ExternalClock),
+            ClockSource::Internal => {
+                Box::new(InternalClock::new(self.bpm).with_send_clock(self.send_clock))
+            }
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            source: default_source(),
+            bpm: default_bpm(),
+            send_clock: false,
+        }
+    }
+}
+
+fn default_source() -> ClockSource {
+    ClockSource::External
+}
+
+fn default_bpm() -> f64 {
+    120.0
+}
+
+fn deserialize_bpm<'de, TDeserializer>(
+    deserializer: TDeserializer,
+) -> std::result::Result<f64, TDeserializer::Error>
+where
+    TDeserializer: Deserializer<'de>,
+{
+    let bpm: f64 = Deserialize::deserialize(deserializer)?;
+    if !(bpm > 0.0 && bpm.is_finite()) {
+        return Err(serde::de::Error::custom(format!(
+            "bpm must be a positive number, got {}",
+            bpm
+        )));
+    }
+    Ok(bpm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_external() {
+        let clock = Clock::default();
+
+        assert_eq!(clock.source, ClockSource::External);
+        assert_eq!(clock.bpm, 120.0);
+        assert!(!clock.send_clock);
+    }
+
+    #[test]
+    fn internal() {
+        let clock: Clock =
+            serde_yaml::from_str("source: internal\nbpm: 92.5\nsend_clock: true").unwrap();
+
+        assert_eq!(clock.source, ClockSource::Internal);
+        assert_eq!(clock.bpm, 92.5);
+        assert!(clock.send_clock);
+    }
+
+    #[test]
+    fn rejects_invalid_bpm() {
+        assert!(serde_yaml::from_str::<Clock>("bpm: 0").is_err());
+        assert!(serde_yaml::from_str::<Clock>("bpm: -10").is_err());
+    }
+}
@@ -2,19 +2,18 @@ use serde::{Deserialize, Deserializer};
 use std::convert::TryInto;
 use wmidi::{Channel, ControlFunction, U7};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Midi {
     pub port: Option<String>,
     pub duration_ratio_slider: Option<MidiSlider>,
-}
-
-impl Default for Midi {
-    fn default() -> Self {
-        Self {
-            port: None,
-            duration_ratio_slider: None,
-        }
-    }
+    #[serde(default, deserialize_with = "deserialize_velocity")]
+    pub default_velocity: Option<u8>,
+    pub output_port: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_channel")]
+    pub output_channel: Option<Channel>,
+    #[serde(default)]
+    pub send_all_notes_off: bool,
+    pub panic_trigger: Option<MidiSlider>,
 }
 
 #[derive(Copy, Clone, Debug, Deserialize)]
@@ -32,7 +31,16 @@ where
     TDeserializer: Deserializer<'de>,
 {
     let channel_number: u8 = Deserialize::deserialize(deserializer)?;
-    Channel::from_index(channel_number - 1).map_err(serde::de::Error::custom)
+    Channel::from_index(channel_number.wrapping_sub(1)).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_optional_channel<'de, TDeserializer>(
+    deserializer: TDeserializer,
+) -> std::result::Result<Option<Channel>, TDeserializer::Error>
+where
+    TDeserializer: Deserializer<'de>,
+{
+    deserialize_channel(deserializer).map(Some)
 }
 
 fn deserialize_control_function<'de, TDeserializer>(
@@ -45,3 +53,46 @@ where
     let value_u7: U7 = value_u8.try_into().map_err(serde::de::Error::custom)?;
     Ok(value_u7.into())
 }
+
+fn deserialize_velocity<'de, TDeserializer>(
+    deserializer: TDeserializer,
+) -> std::result::Result<Option<u8>, TDeserializer::Error>
+where
+    TDeserializer: Deserializer<'de>,
+{
+    let velocity: u8 = Deserialize::deserialize(deserializer)?;
+    if !(1..=127).contains(&velocity) {
+        return Err(serde::de::Error::custom(format!(
+            "velocity must be between 1 and 127, got {}",
+            velocity
+        )));
+    }
+    Ok(Some(velocity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_defaults_to_virtual_port_on_channel_one() {
+        let midi: Midi = serde_yaml::from_str("port: Clock").unwrap();
+
+        assert_eq!(midi.output_port, None);
+        assert_eq!(midi.output_channel, None);
+    }
+
+    #[test]
+    fn output_port_and_channel() {
+        let midi: Midi = serde_yaml::from_str("output_port: Synth\noutput_channel: 10").unwrap();
+
+        assert_eq!(midi.output_port, Some("Synth".to_string()));
+        assert_eq!(midi.output_channel, Some(Channel::Ch10));
+    }
+
+    #[test]
+    fn output_channel_out_of_range() {
+        assert!(serde_yaml::from_str::<Midi>("output_channel: 0").is_err());
+        assert!(serde_yaml::from_str::<Midi>("output_channel: 17").is_err());
+    }
+}
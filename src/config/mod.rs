@@ -1,30 +1,445 @@
+pub mod clock;
 pub mod midi;
 
+use clock::Clock;
 use midi::Midi;
 
-use serde::Deserialize;
+use anyhow::anyhow;
+use serde::{de, Deserialize, Deserializer};
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use crate::{Progression, Result};
+use crate::{import, Line, LineLauncher, Meter, Pitch, Progression, Result};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub midi: Midi,
     #[serde(default)]
+    pub clock: Clock,
+    #[serde(default)]
+    pub key: Option<Pitch>,
+    #[serde(default)]
     pub progression: Progression,
+    #[serde(default)]
+    pub time_signature: Meter,
+    #[serde(default, deserialize_with = "deserialize_lines")]
+    pub lines: Option<Vec<Line>>,
+    #[serde(default)]
+    pub lines_file: Option<PathBuf>,
+    #[serde(default)]
+    pub lines_midi_files: Vec<LinesMidiFile>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LinesMidiFile {
+    pub path: PathBuf,
+    pub root: Pitch,
 }
 
 impl Config {
     pub fn from(yaml: &str) -> Result<Config> {
-        Ok(serde_yaml::from_str(yaml)?)
+        let mut config: Config = serde_yaml::from_str(yaml)?;
+        if let Some(key) = config.key {
+            config.progression = config
+                .progression
+                .in_key(key)
+                .map_err(|err| anyhow!("key: {}", err))?;
+        }
+        Ok(config)
     }
-}
 
-impl Default for Config {
-    fn default() -> Self {
+    pub fn from_file(path: &Path) -> Result<Config> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| anyhow!("Could not read config {}: {}", path.display(), err))?;
+        let config = Config::from(&contents)?;
+        Ok(match path.parent() {
+            Some(config_dir) => config.relative_to(config_dir),
+            None => config,
+        })
+    }
+
+    // Paths in a config file are relative to the file, not the working directory
+    pub fn relative_to(self, dir: &Path) -> Self {
         Self {
-            midi: Midi::default(),
-            progression: Progression::default(),
+            lines_file: self.lines_file.map(|lines_file| dir.join(lines_file)),
+            lines_midi_files: self
+                .lines_midi_files
+                .into_iter()
+                .map(|lines_midi_file| LinesMidiFile {
+                    path: dir.join(lines_midi_file.path),
+                    ..lines_midi_file
+                })
+                .collect(),
+            ..self
+        }
+    }
+
+    pub fn meter(&self) -> Meter {
+        self.progression.meter.unwrap_or(self.time_signature)
+    }
+
+    pub fn lines(&self) -> Result<Vec<Line>> {
+        let mut lines = self.lines.clone().unwrap_or_default();
+
+        if let Some(lines_file) = &self.lines_file {
+            let contents = fs::read_to_string(lines_file).map_err(|err| {
+                anyhow!(
+                    "Could not read lines file {}: {}",
+                    lines_file.display(),
+                    err
+                )
+            })?;
+            lines.extend(parse_lines(
+                contents
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| !is_blank_or_comment(line))
+                    .map(|(index, line)| (format!("{}:{}", lines_file.display(), index + 1), line)),
+            )?);
+        }
+
+        for lines_midi_file in &self.lines_midi_files {
+            lines.extend(import::lines_from_smf_file(
+                &lines_midi_file.path,
+                lines_midi_file.root,
+            )?);
+        }
+
+        if self.lines.is_none() && self.lines_file.is_none() && self.lines_midi_files.is_empty() {
+            return Ok(Line::built_in());
+        }
+
+        if lines.is_empty() {
+            return Err(anyhow!("Config doesn't contain any lines"));
+        }
+
+        Ok(lines)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        let meter = self.meter();
+
+        for (index, bar) in self.progression.bars.iter().enumerate() {
+            if bar.chords.len() as u32 > meter.sixteenths_per_measure() {
+                errors.push(format!(
+                    "progression: bar {} has more chords than a bar of {} has sixteenth notes",
+                    index + 1,
+                    meter
+                ));
+            }
+        }
+
+        match self.lines() {
+            Ok(lines) => {
+                if !lines
+                    .iter()
+                    .any(|line| line.fits_measure(meter.sixteenths_per_measure()))
+                {
+                    errors.push(format!("lines: none of the lines are in {}", meter));
+                } else {
+                    // Nothing at all would be played over these chords
+                    for chord in self.progression.chords_without_lines(&lines, meter) {
+                        errors.push(format!("lines: no line in {} fits {}", meter, chord));
+                    }
+                }
+                for (index, line) in lines.iter().enumerate() {
+                    if let Some(chord) = self.progression.chord_transposing_out_of_range(line) {
+                        errors.push(format!(
+                            "lines: line {} goes above G9 when it's played over {}",
+                            index + 1,
+                            chord
+                        ));
+                    }
+                }
+            }
+            Err(err) => errors.push(err.to_string()),
+        }
+
+        if let (Some(slider), Some(trigger)) =
+            (self.midi.duration_ratio_slider, self.midi.panic_trigger)
+        {
+            if slider.channel == trigger.channel && slider.control_change == trigger.control_change
+            {
+                errors.push(format!(
+                    "midi: duration_ratio_slider and panic_trigger both use control change {} on channel {}",
+                    u8::from(slider.control_change),
+                    slider.channel.number()
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow!("Invalid config:\n{}", errors.join("\n")));
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_ports(
+        &self,
+        port_names: &[String],
+        output_port_names: &[String],
+    ) -> Result<()> {
+        let mut errors = vec![];
+
+        if let Some(port) = &self.midi.port {
+            if !port_names.contains(port) {
+                errors.push(format!(
+                    "midi.port: there is no MIDI input port named '{}'. Available ports are:\n{}",
+                    port,
+                    port_names.join("\n")
+                ));
+            }
+        }
+
+        if let Some(output_port) = &self.midi.output_port {
+            if !output_port_names.contains(output_port) {
+                errors.push(format!(
+                    "midi.output_port: there is no MIDI output port named '{}'. Available output ports are:\n{}",
+                    output_port,
+                    output_port_names.join("\n")
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(anyhow!("Invalid config:\n{}", errors.join("\n")));
+        }
+
+        Ok(())
+    }
+
+    pub fn line_launcher(&self) -> Result<LineLauncher> {
+        let mut line_launcher = LineLauncher::from(self.progression.clone(), self.lines()?)
+            .with_send_all_notes_off(self.midi.send_all_notes_off);
+        if let Some(default_velocity) = self.midi.default_velocity {
+            line_launcher = line_launcher.with_default_velocity(default_velocity);
+        }
+        if let Some(output_channel) = self.midi.output_channel {
+            line_launcher = line_launcher.with_output_channel(output_channel);
+        }
+        if let Some(seed) = self.seed {
+            line_launcher = line_launcher.with_seed(seed);
+        }
+        Ok(line_launcher)
+    }
+}
+
+fn deserialize_lines<'de, TDeserializer>(
+    deserializer: TDeserializer,
+) -> std::result::Result<Option<Vec<Line>>, TDeserializer::Error>
+where
+    TDeserializer: Deserializer<'de>,
+{
+    let line_strings: Vec<String> = Deserialize::deserialize(deserializer)?;
+    parse_lines(
+        line_strings
+            .iter()
+            .enumerate()
+            .map(|(index, line)| (format!("lines[{}]", index), line.as_str())),
+    )
+    .map(Some)
+    .map_err(de::Error::custom)
+}
+
+fn parse_lines<'line>(
+    labeled_lines: impl Iterator<Item = (String, &'line str)>,
+) -> Result<Vec<Line>> {
+    let mut lines = vec![];
+    let mut errors = vec![];
+
+    for (label, line) in labeled_lines {
+        match Line::parse(line) {
+            Ok(parsed) => lines.push(parsed),
+            Err(err) => errors.push(format!("{} ('{}'): {}", label, line, err)),
         }
     }
+
+    if !errors.is_empty() {
+        return Err(anyhow!("Invalid lines:\n{}", errors.join("\n")));
+    }
+
+    Ok(lines)
+}
+
+fn is_blank_or_comment(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meter_defaults_to_four_four() {
+        let config = Config::from("progression: C").unwrap();
+
+        assert_eq!(config.meter(), Meter::new(4, 4));
+    }
+
+    #[test]
+    fn meter_from_progression_overrides_time_signature() {
+        let config = Config::from("time_signature: 3/4\nprogression: C").unwrap();
+        assert_eq!(config.meter(), Meter::new(3, 4));
+
+        let config = Config::from("time_signature: 3/4\nprogression: 5/4 C").unwrap();
+        assert_eq!(config.meter(), Meter::new(5, 4));
+    }
+
+    #[test]
+    fn lines_default_to_built_in() {
+        let config = Config::from("progression: C").unwrap();
+
+        assert_eq!(config.lines().unwrap(), Line::built_in());
+    }
+
+    #[test]
+    fn lines_from_config() {
+        let config = Config::from("lines:\n  - C4 D4 E4\n  - G3 . . C4").unwrap();
+
+        assert_eq!(
+            config.lines().unwrap(),
+            vec![
+                Line::parse("C4 D4 E4").unwrap(),
+                Line::parse("G3 . . C4").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_report_each_invalid_line() {
+        let error = Config::from("lines:\n  - C4 D4 E4\n  - X4\n  - . C4")
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("lines[1] ('X4')"), "{}", error);
+        assert!(error.contains("lines[2] ('. C4')"), "{}", error);
+        assert!(!error.contains("lines[0]"), "{}", error);
+    }
+
+    #[test]
+    fn seed() {
+        assert_eq!(Config::from("seed: 42").unwrap().seed, Some(42));
+        assert_eq!(Config::from("progression: C").unwrap().seed, None);
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let config = Config::from(
+            "progression: 3/4 C
+lines:
+  - C4 D4 E4
+midi:
+  duration_ratio_slider:
+    channel: 1
+    control_change: 7
+  panic_trigger:
+    channel: 1
+    control_change: 7",
+        )
+        .unwrap();
+
+        let error = config.validate().unwrap_err().to_string();
+
+        assert!(error.contains("none of the lines are in 3/4"), "{}", error);
+        assert!(
+            error.contains("both use control change 7 on channel 1"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn validate_rejects_chords_without_lines() {
+        let config = Config::from(
+            "progression: 3/4 Dm7 G7 C
+lines:
+  - C4 D4 E4
+  - \"3/4, dominant: C4 . . . . . . . . . . .\"",
+        )
+        .unwrap();
+
+        let error = config.validate().unwrap_err().to_string();
+
+        assert!(error.contains("no line in 3/4 fits Dm7"), "{}", error);
+        assert!(error.contains("no line in 3/4 fits C"), "{}", error);
+        assert!(!error.contains("G7"), "{}", error);
+    }
+
+    #[test]
+    fn validate_rejects_lines_transposed_out_of_range() {
+        let config = Config::from("progression: C B\nlines:\n  - C4 D4\n  - G9 A8").unwrap();
+
+        let error = config.validate().unwrap_err().to_string();
+
+        assert!(
+            error.contains("line 2 goes above G9 when it's played over B"),
+            "{}",
+            error
+        );
+        assert!(!error.contains("line 1"), "{}", error);
+        assert!(Config::from("progression: C\nlines:\n  - G9 A8")
+            .unwrap()
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn validate_ports() {
+        let config = Config::from("midi:\n  port: Keys\n  output_port: Synth").unwrap();
+        let port_names = vec!["Keys".to_string()];
+
+        assert!(config
+            .validate_ports(&port_names, &["Synth".to_string()])
+            .is_ok());
+        let error = config
+            .validate_ports(&port_names, &["Other".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("no MIDI output port named 'Synth'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn progression_in_key() {
+        let config = Config::from("key: Bb\nprogression: ii7 V7 IM7 vi7").unwrap();
+        assert_eq!(config.progression.to_string(), "Cm7 F7 BbM7 Gm7");
+
+        assert!(Config::from("key: Bb\nprogression: Cm7 F7").is_err());
+    }
+
+    #[test]
+    fn paths_relative_to_config_dir() {
+        let config = Config::from(
+            "lines_file: lines.txt
+lines_midi_files:
+  - path: licks/lick.mid
+    root: G
+  - path: /licks/lick.mid
+    root: C",
+        )
+        .unwrap()
+        .relative_to(Path::new("songs"));
+
+        assert_eq!(config.lines_file, Some(PathBuf::from("songs/lines.txt")));
+        assert_eq!(
+            config
+                .lines_midi_files
+                .iter()
+                .map(|lines_midi_file| lines_midi_file.path.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("songs/licks/lick.mid"),
+                PathBuf::from("/licks/lick.mid")
+            ]
+        );
+    }
 }
@@ -0,0 +1,274 @@
+use anyhow::anyhow;
+use midly::{MetaMessage, MidiMessage, Smf, Timing, TrackEventKind};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use wmidi::Note;
+
+use crate::{
+    beat_number::TICKS_PER_SIXTEENTH_NOTE, Dynamic, Line, LineNote, LinePosition, Meter, Pitch,
+    Result,
+};
+
+const SIXTEENTHS_PER_QUARTER_NOTE: u32 = 4;
+
+struct ImportedNote {
+    start: u32,
+    end: u32,
+    key: u8,
+}
+
+pub fn lines_from_smf_file(path: &Path, root: Pitch) -> Result<Vec<Line>> {
+    let bytes = fs::read(path)
+        .map_err(|err| anyhow!("Could not read MIDI file {}: {}", path.display(), err))?;
+    lines_from_smf(&bytes, root)
+        .map_err(|err| anyhow!("Could not import MIDI file {}: {}", path.display(), err))
+}
+
+pub fn lines_from_smf(bytes: &[u8], root: Pitch) -> Result<Vec<Line>> {
+    let smf = Smf::parse(bytes)?;
+    let ticks_per_quarter_note = match smf.header.timing {
+        Timing::Metrical(ticks_per_quarter_note) => ticks_per_quarter_note.as_int() as u32,
+        Timing::Timecode(..) => return Err(anyhow!("timecode-based MIDI files aren't supported")),
+    };
+    let meter = match smf
+        .tracks
+        .iter()
+        .flatten()
+        .find_map(|event| match event.kind {
+            TrackEventKind::Meta(MetaMessage::TimeSignature(beats, beat_unit_power, _, _)) => {
+                Some((beats, beat_unit_power))
+            }
+            _ => None,
+        }) {
+        Some((beats, beat_unit_power)) => 1u32
+            .checked_shl(beat_unit_power as u32)
+            .and_then(|beat_unit| Meter::checked(beats as u32, beat_unit))
+            .ok_or_else(|| {
+                anyhow!(
+                    "unsupported time signature {}/2^{}, the beat must be a whole to a sixteenth note",
+                    beats,
+                    beat_unit_power
+                )
+            })?,
+        None => Meter::default(),
+    };
+
+    smf.tracks
+        .iter()
+        .map(|track| {
+            let mut ticks = 0;
+            let mut sounding: HashMap<u8, u32> = HashMap::new();
+            let mut notes = vec![];
+            for event in track {
+                ticks += event.delta.as_int();
+                let (key, is_note_on) = match event.kind {
+                    TrackEventKind::Midi {
+                        message: MidiMessage::NoteOn { key, vel },
+                        ..
+                    } => (key.as_int(), vel.as_int() > 0),
+                    TrackEventKind::Midi {
+                        message: MidiMessage::NoteOff { key, .. },
+                        ..
+                    } => (key.as_int(), false),
+                    _ => continue,
+                };
+                if let Some(start) = sounding.remove(&key) {
+                    notes.push(ImportedNote {
+                        start,
+                        end: ticks,
+                        key,
+                    });
+                }
+                if is_note_on {
+                    sounding.insert(key, ticks);
+                }
+            }
+            notes
+        })
+        .filter(|notes| !notes.is_empty())
+        .map(|notes| to_line(notes, ticks_per_quarter_note, meter, root))
+        .collect()
+}
+
+fn to_line(
+    mut notes: Vec<ImportedNote>,
+    ticks_per_quarter_note: u32,
+    meter: Meter,
+    root: Pitch,
+) -> Result<Line> {
+    let quantize = |ticks: u32| {
+        (ticks * SIXTEENTHS_PER_QUARTER_NOTE + ticks_per_quarter_note / 2) / ticks_per_quarter_note
+    };
+    for note in &mut notes {
+        note.start = quantize(note.start);
+        note.end = quantize(note.end);
+    }
+    // Lines are monophonic, so of the notes starting together the highest one wins and each note
+    // is cut off by the next one
+    notes.sort_by_key(|note| (note.start, u8::MAX - note.key));
+    notes.dedup_by_key(|note| note.start);
+
+    let sixteenths_per_measure = meter.sixteenths_per_measure();
+    let first_bar_start = notes[0].start / sixteenths_per_measure * sixteenths_per_measure;
+    let next_starts: Vec<_> = notes
+        .iter()
+        .skip(1)
+        .map(|note| Some(note.start))
+        .chain(std::iter::once(None))
+        .collect();
+
+    let line_notes = notes
+        .iter()
+        .zip(next_starts)
+        .map(|(note, next_start)| {
+            let end = match next_start {
+                Some(next_start) => note.end.min(next_start),
+                None => note.end,
+            };
+            let duration = end.saturating_sub(note.start).max(1);
+            let transposed = note.key as i16 - root.index() as i16;
+            if !(0..=127).contains(&transposed) {
+                return Err(anyhow!("note {} is out of range once transposed", note.key));
+            }
+            Ok(LineNote {
+                start: LinePosition::from_ticks(
+                    (note.start - first_bar_start) * TICKS_PER_SIXTEENTH_NOTE,
+                    sixteenths_per_measure,
+                ),
+                duration_ticks: duration * TICKS_PER_SIXTEENTH_NOTE,
+                note: Note::from_u8_lossy(transposed as u8),
+                dynamic: Dynamic::Normal,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Line::new(line_notes).with_meter(meter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use midly::{
+        num::{u15, u28, u4, u7},
+        Format, Header, TrackEvent,
+    };
+
+    fn smf_bytes(
+        ticks_per_quarter_note: u16,
+        meter: Option<(u8, u8)>,
+        notes: &[(u32, u32, u8)],
+    ) -> Vec<u8> {
+        let mut events: Vec<(u32, TrackEventKind)> = vec![];
+        if let Some((beats, beat_unit_power)) = meter {
+            events.push((
+                0,
+                TrackEventKind::Meta(MetaMessage::TimeSignature(beats, beat_unit_power, 24, 8)),
+            ));
+        }
+        for (start, end, key) in notes {
+            let midi = |message| TrackEventKind::Midi {
+                channel: u4::new(0),
+                message,
+            };
+            events.push((
+                *start,
+                midi(MidiMessage::NoteOn {
+                    key: u7::new(*key),
+                    vel: u7::new(100),
+                }),
+            ));
+            events.push((
+                *end,
+                midi(MidiMessage::NoteOff {
+                    key: u7::new(*key),
+                    vel: u7::new(0),
+                }),
+            ));
+        }
+        events.sort_by_key(|(ticks, _)| *ticks);
+        let mut last_ticks = 0;
+        let mut track: Vec<_> = events
+            .into_iter()
+            .map(|(ticks, kind)| {
+                let delta = u28::new(ticks - last_ticks);
+                last_ticks = ticks;
+                TrackEvent { delta, kind }
+            })
+            .collect();
+        track.push(TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        });
+
+        let mut smf = Smf::new(Header::new(
+            Format::SingleTrack,
+            Timing::Metrical(u15::new(ticks_per_quarter_note)),
+        ));
+        smf.tracks.push(track);
+        let mut bytes = vec![];
+        smf.write_std(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn imported(bytes: &[u8], root: &str) -> Vec<String> {
+        lines_from_smf(bytes, Pitch::parse(root).unwrap())
+            .unwrap()
+            .iter()
+            .map(|line| line.to_notation().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn quantizes_to_sixteenths() {
+        let bytes = smf_bytes(480, None, &[(5, 230, 60), (245, 700, 62), (1430, 1920, 64)]);
+
+        assert_eq!(
+            imported(&bytes, "C"),
+            vec!["C4 . D4 . . . - - - - - - E4 . . ."]
+        );
+    }
+
+    #[test]
+    fn transposes_from_root() {
+        let bytes = smf_bytes(96, None, &[(0, 24, 67), (24, 48, 71), (48, 96, 74)]);
+
+        assert_eq!(imported(&bytes, "G"), vec!["C4 E4 G4 ."]);
+    }
+
+    #[test]
+    fn keeps_top_note_and_drops_leading_bars() {
+        let bytes = smf_bytes(
+            96,
+            Some((3, 2)),
+            &[
+                (288 + 24, 96 * 5, 60),
+                (288 + 24, 96 * 5, 64),
+                (96 * 4, 96 * 5, 67),
+            ],
+        );
+
+        assert_eq!(imported(&bytes, "C"), vec!["3/4: - E4 . . G4 . . ."]);
+    }
+
+    #[test]
+    fn lines_can_be_loaded() {
+        let bytes = smf_bytes(96, None, &[(0, 24, 60), (24, 48, 62)]);
+        let lines = lines_from_smf(&bytes, Pitch::parse("C").unwrap()).unwrap();
+
+        assert_eq!(lines, vec![Line::parse("C4 D4").unwrap()]);
+    }
+
+    #[test]
+    fn rejects_unsupported_meters() {
+        for meter in [(3, 5), (0, 2), (1, 40)] {
+            let bytes = smf_bytes(480, Some(meter), &[(0, 120, 60)]);
+
+            assert!(
+                lines_from_smf(&bytes, Pitch::parse("C").unwrap()).is_err(),
+                "{:?}",
+                meter
+            );
+        }
+    }
+}
@@ -1,23 +1,35 @@
-mod beat_number;
+pub mod beat_number;
+pub mod clock;
 pub mod config;
+pub mod import;
 mod line_launcher;
 pub mod midi;
 mod midi_clock_tracker;
 mod model;
+pub mod render;
 mod result;
+mod tempo_estimator;
 
 pub use beat_number::BeatNumber;
+pub use clock::Clock;
 pub use config::{midi::MidiSlider, Config};
-pub use line_launcher::LineLauncher;
-pub use midi::message::Message;
-pub use midi_clock_tracker::MidiClockTracker;
+pub use line_launcher::{LineLauncher, MidiControls};
+pub use midi::{
+    message::Message,
+    sink::{MidiSink, NullSink, RecordingSink},
+};
+pub use midi_clock_tracker::{ClockMessage, MidiClockTracker};
 pub use model::{
+    bar::Bar,
     chord::Chord,
+    chord_family::ChordFamily,
     letter::Letter,
-    line::{Line, LineNote},
+    line::{Dynamic, Line, LineNote, LinePosition},
+    meter::Meter,
     modifier::Modifier,
     pitch::Pitch,
     progression::Progression,
     quality::Quality,
 };
 pub use result::Result;
+pub use tempo_estimator::TempoEstimator;
@@ -18,6 +18,24 @@ pub fn listen_for_duration_control_changes(
     receiver
 }
 
+pub fn listen_for_panic_trigger(
+    mut midi_messages_receiver: BusReader<Message>,
+    trigger: MidiSlider,
+) -> Receiver<()> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for midi_message in midi_messages_receiver.iter() {
+            match midi::get_control_value(trigger, &midi_message) {
+                Some(control_value) if u8::from(control_value) > 0 => {
+                    sender.send(()).unwrap();
+                }
+                _ => (),
+            }
+        }
+    });
+    receiver
+}
+
 fn control_value_ratio_from_midi_message(
     midi_message: &Message,
     slider: MidiSlider,
@@ -1,41 +1,49 @@
-use midir::MidiOutputConnection;
+use crate::MidiSink;
 use std::sync::{Arc, Mutex};
-use wmidi::{Channel, MidiMessage, Note, Velocity};
+use wmidi::{Channel, ControlFunction, MidiMessage, Note, Velocity, U7};
 
-const CHANNEL: Channel = Channel::Ch1;
-const VELOCITY: u8 = 100;
+pub const DEFAULT_VELOCITY: u8 = 100;
+const NOTE_OFF_VELOCITY: u8 = 100;
 
 #[derive(Clone)]
 pub struct MidiMessageSender {
-    output: Arc<Mutex<MidiOutputConnection>>,
+    output: Arc<Mutex<Box<dyn MidiSink>>>,
+    channel: Channel,
 }
 
 impl MidiMessageSender {
-    pub fn new(output: MidiOutputConnection) -> Self {
+    pub fn new(output: impl MidiSink + 'static, channel: Channel) -> Self {
         Self {
-            output: Arc::new(Mutex::new(output)),
+            output: Arc::new(Mutex::new(Box::new(output))),
+            channel,
         }
     }
 
-    pub fn fire_note_on(&self, note: Note) {
+    pub fn fire_note_on(&self, note: Note, velocity: u8) {
         self.send_midi_message(MidiMessage::NoteOn(
-            CHANNEL,
+            self.channel,
             note,
-            Velocity::from_u8_lossy(VELOCITY),
+            Velocity::from_u8_lossy(velocity),
         ));
     }
 
     pub fn fire_note_off(&self, note: Note) {
         self.send_midi_message(MidiMessage::NoteOff(
-            CHANNEL,
+            self.channel,
             note,
-            Velocity::from_u8_lossy(VELOCITY),
+            Velocity::from_u8_lossy(NOTE_OFF_VELOCITY),
+        ));
+    }
+
+    pub fn fire_all_notes_off(&self) {
+        self.send_midi_message(MidiMessage::ControlChange(
+            self.channel,
+            ControlFunction::ALL_NOTES_OFF,
+            U7::MIN,
         ));
     }
 
     pub fn send_midi_message(&self, midi_message: MidiMessage) {
-        let mut bytes_buffer = vec![0; midi_message.bytes_size()];
-        midi_message.copy_to_slice(&mut bytes_buffer).unwrap();
-        self.output.lock().unwrap().send(&bytes_buffer).unwrap();
+        self.output.lock().unwrap().send(&midi_message).unwrap();
     }
 }
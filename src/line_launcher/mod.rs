@@ -1,127 +1,156 @@
 use bus::Bus;
-use midir::MidiOutputConnection;
-use rand::Rng;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::cell::RefCell;
 use std::sync::{
+    atomic::{AtomicU32, Ordering},
     mpsc::{self, Receiver, Sender},
     Arc, Mutex,
 };
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::Instant;
+use wmidi::{Channel, MidiMessage, Note};
 
-use crate::{BeatNumber, Line, Message, MidiSlider, Progression};
+use crate::{
+    BeatNumber, Chord, ClockMessage, Line, Message, Meter, MidiClockTracker, MidiSink, MidiSlider,
+    Progression, Result, TempoEstimator,
+};
 
 mod midi_message_sender;
-use midi_message_sender::MidiMessageSender;
+use midi_message_sender::{MidiMessageSender, DEFAULT_VELOCITY};
 
-mod note_off_triggerer;
-use note_off_triggerer::{NoteOffInstruction, NoteOffTriggerer};
+mod note_off_scheduler;
+use note_off_scheduler::{NoteOffId, NoteOffScheduler};
 
 mod playing_state;
-use playing_state::PlayingState;
+use playing_state::{PlayingState, SoundingNote};
 
 mod progression_state;
 use progression_state::ProgressionState;
 
 mod duration_slider_listener;
-use duration_slider_listener::listen_for_duration_control_changes;
-
-enum DurationBetweenSixteenthNotes {
-    Uninitialized,
-    PartiallyInitialized {
-        last_timestamp: SystemTime,
-    },
-    Initialized {
-        last_timestamp: SystemTime,
-        last_duration: Duration,
-    },
-}
-
-impl DurationBetweenSixteenthNotes {
-    pub fn new() -> Self {
-        Self::Uninitialized
-    }
-
-    pub fn process_beat_message(&self, _beat_message: &BeatNumber) -> Self {
-        let now = SystemTime::now();
-        match self {
-            Self::Uninitialized => Self::PartiallyInitialized {
-                last_timestamp: now,
-            },
-            Self::PartiallyInitialized { last_timestamp }
-            | Self::Initialized { last_timestamp, .. } => Self::Initialized {
-                last_timestamp: now,
-                last_duration: now.duration_since(*last_timestamp).unwrap(),
-            },
-        }
-    }
-
-    pub fn get_duration(&self) -> Option<Duration> {
-        match self {
-            Self::Initialized { last_duration, .. } => Some(*last_duration),
-            _ => None,
-        }
-    }
-}
+use duration_slider_listener::{listen_for_duration_control_changes, listen_for_panic_trigger};
 
 pub enum CombinedMessage {
-    BeatMessage(BeatNumber),
+    ClockMessage(ClockMessage),
     DurationRatioMessage(f64),
+    Reload(Progression, Vec<Line>),
+    Panic,
+    Shutdown,
 }
 
 pub fn get_combined_message_receiver(
-    beat_message_receiver: Receiver<BeatNumber>,
+    clock_message_receiver: Receiver<ClockMessage>,
     duration_ratio_receiver: Receiver<f64>,
+    reload_receiver: Receiver<(Progression, Vec<Line>)>,
+    panic_receiver: Receiver<()>,
+    shutdown_receiver: Receiver<()>,
 ) -> Receiver<CombinedMessage> {
     let (sender, receiver) = mpsc::channel();
-    let beat_message_sender = sender.clone();
-    thread::spawn(move || {
-        for beat_message in beat_message_receiver.iter() {
-            beat_message_sender
-                .send(CombinedMessage::BeatMessage(beat_message))
-                .unwrap();
-        }
+    forward(
+        clock_message_receiver,
+        sender.clone(),
+        CombinedMessage::ClockMessage,
+    );
+    forward(
+        duration_ratio_receiver,
+        sender.clone(),
+        CombinedMessage::DurationRatioMessage,
+    );
+    forward(reload_receiver, sender.clone(), |(progression, lines)| {
+        CombinedMessage::Reload(progression, lines)
     });
+    forward(panic_receiver, sender.clone(), |_| CombinedMessage::Panic);
+    forward(shutdown_receiver, sender, |_| CombinedMessage::Shutdown);
+    receiver
+}
+
+fn forward<TMessage: Send + 'static>(
+    receiver: Receiver<TMessage>,
+    sender: Sender<CombinedMessage>,
+    to_combined_message: impl Fn(TMessage) -> CombinedMessage + Send + 'static,
+) {
     thread::spawn(move || {
-        for duration_ratio_message in duration_ratio_receiver.iter() {
-            sender
-                .send(CombinedMessage::DurationRatioMessage(
-                    duration_ratio_message,
-                ))
-                .unwrap();
+        for message in receiver.iter() {
+            if sender.send(to_combined_message(message)).is_err() {
+                break;
+            }
         }
     });
-    receiver
+}
+
+#[derive(Default)]
+pub struct MidiControls {
+    pub midi_messages: Option<Receiver<Message>>,
+    pub duration_ratio_slider: Option<MidiSlider>,
+    pub panic_trigger: Option<MidiSlider>,
 }
 
 pub struct LineLauncher {
     lines: Vec<Line>,
     pub progression: Progression,
+    default_velocity: u8,
+    output_channel: Channel,
+    send_all_notes_off: bool,
+    // Unlike StdRng, ChaCha8Rng gives the same values for a seed on every platform and version
+    rng: RefCell<ChaCha8Rng>,
 }
 
 impl LineLauncher {
-    pub fn from(progression: Progression) -> Self {
+    pub fn from(progression: Progression, lines: Vec<Line>) -> Self {
         Self {
-            lines: Line::all(),
+            lines,
             progression,
+            default_velocity: DEFAULT_VELOCITY,
+            output_channel: Channel::Ch1,
+            send_all_notes_off: false,
+            rng: RefCell::new(ChaCha8Rng::from_entropy()),
+        }
+    }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            rng: RefCell::new(ChaCha8Rng::seed_from_u64(seed)),
+            ..self
+        }
+    }
+
+    pub fn with_send_all_notes_off(self, send_all_notes_off: bool) -> Self {
+        Self {
+            send_all_notes_off,
+            ..self
+        }
+    }
+
+    pub fn with_output_channel(self, output_channel: Channel) -> Self {
+        Self {
+            output_channel,
+            ..self
+        }
+    }
+
+    pub fn with_default_velocity(self, default_velocity: u8) -> Self {
+        Self {
+            default_velocity,
+            ..self
         }
     }
 
     pub fn listen(
         &self,
-        beat_message_receiver: Receiver<BeatNumber>,
-        output: MidiOutputConnection,
-        midi_messages: Option<Receiver<Message>>,
-        duration_ratio_slider: Option<MidiSlider>,
+        clock_message_receiver: Receiver<ClockMessage>,
+        output: impl MidiSink + 'static,
+        midi_controls: MidiControls,
+        reload_receiver: Receiver<(Progression, Vec<Line>)>,
+        shutdown_receiver: Receiver<()>,
     ) {
-        let midi_message_sender = MidiMessageSender::new(output);
-        let state_mutex = Arc::new(Mutex::new(PlayingState::NotPlaying));
-        let mut progression_state = ProgressionState::new(&self.progression);
-        let (note_off_triggerer, note_off_sender) =
-            NoteOffTriggerer::new(midi_message_sender.clone(), state_mutex.clone());
-        note_off_triggerer.listen();
-        let mut duration_between_sixteenth_notes = DurationBetweenSixteenthNotes::new();
+        let MidiControls {
+            midi_messages,
+            duration_ratio_slider,
+            panic_trigger,
+        } = midi_controls;
         let mut midi_message_bus = Bus::new(100);
-        let (mut duration_ratio, duration_ratio_receiver) = match duration_ratio_slider {
+        let (duration_ratio, duration_ratio_receiver) = match duration_ratio_slider {
             Some(duration_ratio_slider) => (
                 Some(1.0),
                 listen_for_duration_control_changes(
@@ -134,6 +163,15 @@ impl LineLauncher {
                 receiver
             }),
         };
+        let panic_receiver = match panic_trigger {
+            Some(panic_trigger) => {
+                listen_for_panic_trigger(midi_message_bus.add_rx(), panic_trigger)
+            }
+            None => {
+                let (_sender, receiver) = mpsc::channel();
+                receiver
+            }
+        };
         if let Some(midi_messages) = midi_messages {
             thread::spawn(move || {
                 for midi_message in midi_messages.iter() {
@@ -141,115 +179,241 @@ impl LineLauncher {
                 }
             });
         }
-        for message in
-            get_combined_message_receiver(beat_message_receiver, duration_ratio_receiver).iter()
+        let mut performance = Performance::new(self, output, duration_ratio);
+        for message in get_combined_message_receiver(
+            clock_message_receiver,
+            duration_ratio_receiver,
+            reload_receiver,
+            panic_receiver,
+            shutdown_receiver,
+        )
+        .iter()
         {
-            match message {
-                CombinedMessage::BeatMessage(beat_message) => {
-                    duration_between_sixteenth_notes =
-                        duration_between_sixteenth_notes.process_beat_message(&beat_message);
-                    if beat_message.is_beginning_of_measure() {
-                        progression_state.tick_measure();
+            if let CombinedMessage::Shutdown = message {
+                break;
+            }
+            performance.handle(message);
+        }
+        performance.finish();
+    }
+
+    pub fn render(&self, meter: Meter, num_ticks: u32) -> Vec<(u32, MidiMessage<'static>)> {
+        let sink = TickStampedSink::default();
+        let (mut midi_clock_tracker, clock_message_receiver) = MidiClockTracker::new(meter);
+        for _ in 0..num_ticks {
+            midi_clock_tracker.tick().unwrap();
+        }
+
+        let mut performance = Performance::new(self, sink.clone(), None);
+        for (tick, clock_message) in clock_message_receiver.try_iter().enumerate() {
+            sink.set_tick(tick as u32);
+            performance.handle(CombinedMessage::ClockMessage(clock_message));
+        }
+        sink.set_tick(num_ticks);
+        performance.finish();
+
+        sink.midi_messages()
+    }
+}
+
+struct Performance<'launcher> {
+    line_launcher: &'launcher LineLauncher,
+    lines: Vec<Line>,
+    pending_reload: Option<(Progression, Vec<Line>)>,
+    midi_message_sender: MidiMessageSender,
+    note_off_scheduler: NoteOffScheduler,
+    state: PlayingState,
+    progression_state: ProgressionState,
+    tempo_estimator: TempoEstimator,
+    duration_ratio: Option<f64>,
+}
+
+impl<'launcher> Performance<'launcher> {
+    fn new(
+        line_launcher: &'launcher LineLauncher,
+        output: impl MidiSink + 'static,
+        duration_ratio: Option<f64>,
+    ) -> Self {
+        let midi_message_sender = MidiMessageSender::new(output, line_launcher.output_channel);
+        let note_off_scheduler = {
+            let midi_message_sender = midi_message_sender.clone();
+            NoteOffScheduler::new(move |note| midi_message_sender.fire_note_off(note))
+        };
+        Self {
+            line_launcher,
+            lines: line_launcher.lines.clone(),
+            pending_reload: None,
+            midi_message_sender,
+            note_off_scheduler,
+            state: PlayingState::NotPlaying,
+            progression_state: ProgressionState::new(line_launcher.progression.clone()),
+            tempo_estimator: TempoEstimator::new(),
+            duration_ratio,
+        }
+    }
+
+    fn handle(&mut self, message: CombinedMessage) {
+        match message {
+            CombinedMessage::ClockMessage(ClockMessage::Start) => {
+                self.state = silence(&self.note_off_scheduler);
+                self.progression_state.reset();
+                self.tempo_estimator.reset();
+            }
+            CombinedMessage::ClockMessage(ClockMessage::Stop) => {
+                self.state = silence(&self.note_off_scheduler);
+            }
+            CombinedMessage::ClockMessage(ClockMessage::SongPosition {
+                measure,
+                beat_number,
+            }) => {
+                self.state = silence(&self.note_off_scheduler);
+                self.progression_state.jump_to(measure, beat_number);
+            }
+            CombinedMessage::ClockMessage(ClockMessage::Continue) => {
+                self.tempo_estimator.reset();
+            }
+            CombinedMessage::ClockMessage(ClockMessage::Beat(beat_message, at)) => {
+                if beat_message.is_beginning_of_measure() {
+                    if let Some((progression, lines)) = self.pending_reload.take() {
+                        self.state = silence(&self.note_off_scheduler);
+                        self.progression_state.set_progression(progression);
+                        self.lines = lines;
                     }
-                    let mut state = state_mutex.lock().unwrap();
-                    *state = match *state {
-                        PlayingState::NotPlaying if beat_message.is_beginning_of_measure() => {
-                            *state = PlayingState::Playing {
-                                line_index: rand::thread_rng().gen_range(0..self.lines.len()),
-                                next_note_index: 0,
-                                pitch_offset: progression_state.current_chord().pitch.index(),
-                                has_fired_previous_note_off: true,
-                            };
-                            self.possibly_trigger_notes(
-                                *state,
-                                &midi_message_sender,
-                                beat_message,
-                                &note_off_sender,
-                                &duration_between_sixteenth_notes,
-                                duration_ratio,
-                            )
-                        }
-                        PlayingState::Playing { .. } => self.possibly_trigger_notes(
-                            *state,
-                            &midi_message_sender,
-                            beat_message,
-                            &note_off_sender,
-                            &duration_between_sixteenth_notes,
-                            duration_ratio,
-                        ),
-                        _ => *state,
-                    };
                 }
-                CombinedMessage::DurationRatioMessage(new_duration_ratio) => {
-                    duration_ratio = Some(new_duration_ratio);
+                self.tempo_estimator.tick(at);
+                self.progression_state.tick(beat_message);
+                self.state = self.next_state(beat_message);
+            }
+            CombinedMessage::DurationRatioMessage(new_duration_ratio) => {
+                self.duration_ratio = Some(new_duration_ratio);
+                self.state = self.reschedule_note_off();
+            }
+            CombinedMessage::Reload(progression, lines) => {
+                self.pending_reload = Some((progression, lines));
+            }
+            CombinedMessage::Panic => {
+                self.state = silence(&self.note_off_scheduler);
+                if self.line_launcher.send_all_notes_off {
+                    self.midi_message_sender.fire_all_notes_off();
                 }
             }
+            CombinedMessage::Shutdown => (),
         }
     }
 
-    fn possibly_trigger_notes(
-        &self,
-        state: PlayingState,
-        midi_message_sender: &MidiMessageSender,
-        beat_message: BeatNumber,
-        note_off_sender: &Sender<NoteOffInstruction>,
-        duration_between_sixteenth_notes: &DurationBetweenSixteenthNotes,
-        duration_ratio: Option<f64>,
-    ) -> PlayingState {
+    fn next_state(&self, beat_message: BeatNumber) -> PlayingState {
+        match self.state {
+            PlayingState::NotPlaying if self.progression_state.is_at_chord_change() => {
+                let current_chord = self.progression_state.current_chord();
+                let line_index = match self
+                    .choose_line_index(current_chord, beat_message.sixteenths_per_measure)
+                {
+                    Some(line_index) => line_index,
+                    None => return self.state,
+                };
+                self.possibly_trigger_notes(
+                    PlayingState::Playing {
+                        line_index,
+                        next_note_index: 0,
+                        sounding_note: None,
+                        ticks_since_start: 0,
+                    },
+                    current_chord,
+                )
+            }
+            PlayingState::Playing { .. } => self.possibly_trigger_notes(
+                self.state.next_tick(),
+                self.progression_state.current_chord(),
+            ),
+            _ => self.state,
+        }
+    }
+
+    fn choose_line_index(&self, chord: &Chord, sixteenths_per_measure: u32) -> Option<usize> {
+        let fitting_line_indices: Vec<_> = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.fits(chord) && line.fits_measure(sixteenths_per_measure))
+            .map(|(line_index, _)| line_index)
+            .collect();
+        fitting_line_indices
+            .choose(&mut *self.line_launcher.rng.borrow_mut())
+            .copied()
+    }
+
+    fn possibly_trigger_notes(&self, state: PlayingState, current_chord: &Chord) -> PlayingState {
         match state {
             PlayingState::Playing {
                 line_index,
                 next_note_index,
-                pitch_offset,
-                has_fired_previous_note_off,
+                mut sounding_note,
+                ticks_since_start,
             } => {
                 let line = &self.lines[line_index];
-                let mut did_trigger_note_off = false;
-                if next_note_index > 0 {
+                let sixteenths_per_measure = line.meter.sixteenths_per_measure();
+                if let Some(SoundingNote { note_off_id, .. }) = sounding_note {
                     let last_played_note = &line.notes[next_note_index - 1];
-                    if !has_fired_previous_note_off
-                        && beat_message.minus_sixteenths(last_played_note.duration)
-                            == last_played_note.start
+                    if last_played_note.start.to_ticks(sixteenths_per_measure)
+                        + last_played_note.duration_ticks
+                        == ticks_since_start
                     {
-                        midi_message_sender
-                            .fire_note_off(last_played_note.note.step(pitch_offset).unwrap());
-                        did_trigger_note_off = true;
+                        self.note_off_scheduler.release(note_off_id);
+                        sounding_note = None;
                     }
                 }
                 if next_note_index == line.notes.len() {
-                    return if did_trigger_note_off || has_fired_previous_note_off {
-                        PlayingState::NotPlaying
-                    } else {
-                        state
+                    return match sounding_note {
+                        None => PlayingState::NotPlaying,
+                        Some(_) => PlayingState::Playing {
+                            line_index,
+                            next_note_index,
+                            sounding_note,
+                            ticks_since_start,
+                        },
                     };
                 }
                 let next_note = &line.notes[next_note_index];
-                if beat_message == next_note.start {
-                    let next_note_with_offset = next_note.note.step(pitch_offset).unwrap();
-                    midi_message_sender.fire_note_on(next_note_with_offset);
-                    if let Some(duration_ratio) = duration_ratio {
-                        if let Some(duration_between_sixteenth_notes) =
-                            duration_between_sixteenth_notes.get_duration()
-                        {
-                            note_off_sender
-                                .send(NoteOffInstruction {
-                                    note: next_note_with_offset,
-                                    time: SystemTime::now()
-                                        + duration_between_sixteenth_notes.mul_f64(duration_ratio),
-                                    note_index: next_note_index,
-                                })
-                                .unwrap();
+                if next_note.start.to_ticks(sixteenths_per_measure) == ticks_since_start {
+                    let pitch_offset = line.anchor_pitch(current_chord).index();
+                    let next_note_with_offset = match next_note.note.step(pitch_offset) {
+                        Ok(next_note_with_offset) => next_note_with_offset,
+                        // Transposed above G9. Config::validate() rejects such lines, but
+                        // a LineLauncher can be built without it
+                        Err(_) => {
+                            return PlayingState::Playing {
+                                line_index,
+                                next_note_index: next_note_index + 1,
+                                sounding_note,
+                                ticks_since_start,
+                            }
                         }
-                    }
+                    };
+                    self.midi_message_sender.fire_note_on(
+                        next_note_with_offset,
+                        next_note
+                            .dynamic
+                            .velocity(self.line_launcher.default_velocity),
+                    );
+                    let started = Instant::now();
                     return PlayingState::Playing {
                         line_index,
                         next_note_index: next_note_index + 1,
-                        pitch_offset,
-                        has_fired_previous_note_off: false,
+                        sounding_note: Some(SoundingNote {
+                            note_off_id: self.schedule_note_off(next_note_with_offset, started),
+                            note: next_note_with_offset,
+                            started,
+                        }),
+                        ticks_since_start,
                     };
                 }
 
-                state
+                PlayingState::Playing {
+                    line_index,
+                    next_note_index,
+                    sounding_note,
+                    ticks_since_start,
+                }
             }
             _ => {
                 panic!(
@@ -259,4 +423,349 @@ impl LineLauncher {
             }
         }
     }
+
+    fn note_off_time(&self, started: Instant) -> Option<Instant> {
+        let duration_ratio = self.duration_ratio?;
+        let sixteenth_note_duration = self.tempo_estimator.sixteenth_note_duration()?;
+        Some(started + sixteenth_note_duration.mul_f64(duration_ratio))
+    }
+
+    fn schedule_note_off(&self, note: Note, started: Instant) -> NoteOffId {
+        match self.note_off_time(started) {
+            Some(time) => self.note_off_scheduler.schedule(note, time),
+            None => self.note_off_scheduler.hold(note),
+        }
+    }
+
+    // Moving the duration slider also moves the note off of the sounding note, unless it has
+    // already been sent
+    fn reschedule_note_off(&self) -> PlayingState {
+        match self.state {
+            PlayingState::Playing {
+                line_index,
+                next_note_index,
+                sounding_note: Some(sounding_note),
+                ticks_since_start,
+            } if self.note_off_time(sounding_note.started).is_some()
+                && self.note_off_scheduler.cancel(sounding_note.note_off_id) =>
+            {
+                PlayingState::Playing {
+                    line_index,
+                    next_note_index,
+                    sounding_note: Some(SoundingNote {
+                        note_off_id: self
+                            .schedule_note_off(sounding_note.note, sounding_note.started),
+                        ..sounding_note
+                    }),
+                    ticks_since_start,
+                }
+            }
+            _ => self.state,
+        }
+    }
+
+    fn finish(self) {
+        self.note_off_scheduler.shutdown();
+        if self.line_launcher.send_all_notes_off {
+            self.midi_message_sender.fire_all_notes_off();
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct TickStampedSink {
+    tick: Arc<AtomicU32>,
+    midi_messages: Arc<Mutex<Vec<(u32, MidiMessage<'static>)>>>,
+}
+
+impl TickStampedSink {
+    fn set_tick(&self, tick: u32) {
+        self.tick.store(tick, Ordering::SeqCst);
+    }
+
+    fn midi_messages(&self) -> Vec<(u32, MidiMessage<'static>)> {
+        self.midi_messages.lock().unwrap().clone()
+    }
+}
+
+impl MidiSink for TickStampedSink {
+    fn send(&mut self, midi_message: &MidiMessage) -> Result<()> {
+        self.midi_messages
+            .lock()
+            .unwrap()
+            .push((self.tick.load(Ordering::SeqCst), midi_message.to_owned()));
+        Ok(())
+    }
+}
+
+fn silence(note_off_scheduler: &NoteOffScheduler) -> PlayingState {
+    note_off_scheduler.release_all();
+    PlayingState::NotPlaying
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RecordingSink;
+    use std::time::Duration;
+    use wmidi::{Note, Velocity};
+
+    fn played(
+        progression: &str,
+        line: &str,
+        play: impl FnOnce(&mut MidiClockTracker),
+    ) -> Vec<MidiMessage<'static>> {
+        let line_launcher = LineLauncher::from(
+            Progression::parse(progression).unwrap(),
+            vec![Line::parse(line).unwrap()],
+        );
+        played_by(line_launcher, play)
+    }
+
+    fn played_by(
+        line_launcher: LineLauncher,
+        play: impl FnOnce(&mut MidiClockTracker),
+    ) -> Vec<MidiMessage<'static>> {
+        let (mut midi_clock_tracker, clock_message_receiver) =
+            MidiClockTracker::new(line_launcher.progression.meter.unwrap_or_default());
+        play(&mut midi_clock_tracker);
+        drop(midi_clock_tracker);
+        let (_, reload_receiver) = mpsc::channel();
+        let (_, shutdown_receiver) = mpsc::channel();
+        let sink = RecordingSink::new();
+
+        line_launcher.listen(
+            clock_message_receiver,
+            sink.clone(),
+            MidiControls::default(),
+            reload_receiver,
+            shutdown_receiver,
+        );
+
+        sink.midi_messages()
+    }
+
+    fn tick(midi_clock_tracker: &mut MidiClockTracker, num_ticks: u32) {
+        for _ in 0..num_ticks {
+            midi_clock_tracker.tick().unwrap();
+        }
+    }
+
+    fn note_on(note: Note, velocity: u8) -> MidiMessage<'static> {
+        MidiMessage::NoteOn(Channel::Ch1, note, Velocity::from_u8_lossy(velocity))
+    }
+
+    fn note_off(note: Note) -> MidiMessage<'static> {
+        MidiMessage::NoteOff(Channel::Ch1, note, Velocity::from_u8_lossy(100))
+    }
+
+    #[test]
+    fn plays_line_over_each_chord() {
+        let midi_messages = played("C F", "C4 D4 . E4", |midi_clock_tracker| {
+            tick(midi_clock_tracker, 24 * 8)
+        });
+
+        assert_eq!(
+            midi_messages,
+            vec![
+                note_on(Note::C4, 100),
+                note_off(Note::C4),
+                note_on(Note::D4, 100),
+                note_off(Note::D4),
+                note_on(Note::E4, 100),
+                note_off(Note::E4),
+                note_on(Note::F4, 100),
+                note_off(Note::F4),
+                note_on(Note::G4, 100),
+                note_off(Note::G4),
+                note_on(Note::A4, 100),
+                note_off(Note::A4),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_notes_are_released_before_being_played_again() {
+        let midi_messages = played("C", "C4 C4 >C4", |midi_clock_tracker| {
+            tick(midi_clock_tracker, 24)
+        });
+
+        assert_eq!(
+            midi_messages,
+            vec![
+                note_on(Note::C4, 100),
+                note_off(Note::C4),
+                note_on(Note::C4, 100),
+                note_off(Note::C4),
+                note_on(Note::C4, 127),
+                note_off(Note::C4),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_notes_transposed_out_of_range() {
+        let midi_messages = played("B", "C4 G9 D4", |midi_clock_tracker| {
+            tick(midi_clock_tracker, 24)
+        });
+
+        assert_eq!(
+            midi_messages,
+            vec![
+                note_on(Note::B4, 100),
+                note_off(Note::B4),
+                note_on(Note::CSharp5, 100),
+                note_off(Note::CSharp5),
+            ]
+        );
+    }
+
+    #[test]
+    fn duration_slider_moves_the_sounding_note_off() {
+        let line_launcher = LineLauncher::from(
+            Progression::parse("C").unwrap(),
+            vec![Line::parse("C4 . . . . . . . . . . . . . . .").unwrap()],
+        );
+        let sink = RecordingSink::new();
+        let mut performance = Performance::new(&line_launcher, sink.clone(), Some(1.0));
+        let (mut midi_clock_tracker, clock_message_receiver) =
+            MidiClockTracker::new(Meter::new(4, 4));
+        let start = Instant::now();
+        for tick in 0..12 {
+            midi_clock_tracker
+                .tick_at(start + Duration::from_millis(100 * tick))
+                .unwrap();
+        }
+        for clock_message in clock_message_receiver.try_iter() {
+            performance.handle(CombinedMessage::ClockMessage(clock_message));
+        }
+        assert_eq!(sink.midi_messages(), vec![note_on(Note::C4, 100)]);
+
+        performance.handle(CombinedMessage::DurationRatioMessage(0.0));
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while sink.midi_messages().len() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(
+            sink.midi_messages(),
+            vec![note_on(Note::C4, 100), note_off(Note::C4)]
+        );
+
+        performance.finish();
+        assert_eq!(sink.midi_messages().len(), 2);
+    }
+
+    #[test]
+    fn stop_releases_sounding_note() {
+        let midi_messages = played("C", "C4 . . . D4", |midi_clock_tracker| {
+            tick(midi_clock_tracker, 3);
+            midi_clock_tracker.stop().unwrap();
+            tick(midi_clock_tracker, 24);
+        });
+
+        assert_eq!(
+            midi_messages,
+            vec![note_on(Note::C4, 100), note_off(Note::C4)]
+        );
+    }
+
+    #[test]
+    fn releases_sounding_note_when_the_clock_ends() {
+        let midi_messages = played("G", "C4 . . .", |midi_clock_tracker| {
+            tick(midi_clock_tracker, 12)
+        });
+
+        assert_eq!(
+            midi_messages,
+            vec![note_on(Note::G4, 100), note_off(Note::G4)]
+        );
+    }
+
+    fn notes_played_with_seed(seed: u64) -> Vec<Note> {
+        let lines = ["C4", "D4", "E4", "F4"]
+            .iter()
+            .map(|line| Line::parse(line).unwrap())
+            .collect();
+        let line_launcher =
+            LineLauncher::from(Progression::parse("C C C C C C C C").unwrap(), lines)
+                .with_seed(seed);
+        played_by(line_launcher, |midi_clock_tracker| {
+            tick(midi_clock_tracker, 24 * 4 * 8)
+        })
+        .into_iter()
+        .filter_map(|midi_message| match midi_message {
+            MidiMessage::NoteOn(_, note, _) => Some(note),
+            _ => None,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn seed_makes_line_choice_repeatable() {
+        assert_eq!(
+            notes_played_with_seed(1),
+            vec![
+                Note::E4,
+                Note::C4,
+                Note::E4,
+                Note::F4,
+                Note::D4,
+                Note::C4,
+                Note::F4,
+                Note::C4
+            ]
+        );
+    }
+
+    #[test]
+    fn render_stamps_messages_with_ticks() {
+        let line_launcher = LineLauncher::from(
+            Progression::parse("C F").unwrap(),
+            vec![Line::parse("C4 D4 . E4").unwrap()],
+        );
+
+        let midi_messages = line_launcher.render(Meter::new(4, 4), 24 * 8);
+
+        assert_eq!(midi_messages.len(), 12);
+        assert_eq!(midi_messages[0], (0, note_on(Note::C4, 100)));
+        assert_eq!(midi_messages[3], (18, note_off(Note::D4)));
+        assert_eq!(midi_messages[6], (96, note_on(Note::F4, 100)));
+        assert_eq!(midi_messages[11], (120, note_off(Note::A4)));
+    }
+
+    #[test]
+    fn reload_switches_progression_and_lines_at_the_next_bar_line() {
+        let line_launcher = LineLauncher::from(
+            Progression::parse("C").unwrap(),
+            vec![Line::parse("C4 | D4").unwrap()],
+        );
+        let sink = RecordingSink::new();
+        let mut performance = Performance::new(&line_launcher, sink.clone(), None);
+        let (mut midi_clock_tracker, clock_message_receiver) =
+            MidiClockTracker::new(Meter::new(4, 4));
+        tick(&mut midi_clock_tracker, 24 * 4 * 2);
+
+        for (tick, clock_message) in clock_message_receiver.try_iter().enumerate() {
+            if tick == 24 {
+                performance.handle(CombinedMessage::Reload(
+                    Progression::parse("F").unwrap(),
+                    vec![Line::parse("C4 D4").unwrap()],
+                ));
+            }
+            performance.handle(CombinedMessage::ClockMessage(clock_message));
+        }
+        performance.finish();
+
+        assert_eq!(
+            sink.midi_messages(),
+            vec![
+                note_on(Note::C4, 100),
+                note_off(Note::C4),
+                note_on(Note::F4, 100),
+                note_off(Note::F4),
+                note_on(Note::G4, 100),
+                note_off(Note::G4),
+            ]
+        );
+    }
 }
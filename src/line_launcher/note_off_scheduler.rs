@@ -0,0 +1,243 @@
+use std::cell::Cell;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+use wmidi::Note;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NoteOffId(u64);
+
+type Deadline = Reverse<(Instant, NoteOffId)>;
+
+// Held and scheduled notes share one map so that releasing a note from the
+// launcher's thread fires its note off right away, in order with the note ons
+// around it, while the scheduler thread only keeps track of the deadlines.
+struct PendingNotes {
+    notes: Mutex<BTreeMap<NoteOffId, Note>>,
+    fire_note_off: Box<dyn Fn(Note) + Send + Sync>,
+}
+
+impl PendingNotes {
+    fn insert(&self, id: NoteOffId, note: Note) {
+        self.notes.lock().unwrap().insert(id, note);
+    }
+
+    fn release(&self, id: NoteOffId) {
+        let mut notes = self.notes.lock().unwrap();
+        if let Some(note) = notes.remove(&id) {
+            (self.fire_note_off)(note);
+        }
+    }
+
+    fn cancel(&self, id: NoteOffId) -> bool {
+        self.notes.lock().unwrap().remove(&id).is_some()
+    }
+
+    fn release_all(&self) {
+        let mut notes = self.notes.lock().unwrap();
+        for (_, note) in std::mem::take(&mut *notes) {
+            (self.fire_note_off)(note);
+        }
+    }
+}
+
+pub struct NoteOffScheduler {
+    pending_notes: Arc<PendingNotes>,
+    deadline_sender: Sender<Deadline>,
+    next_id: Cell<u64>,
+    thread: JoinHandle<()>,
+}
+
+impl NoteOffScheduler {
+    pub fn new(fire_note_off: impl Fn(Note) + Send + Sync + 'static) -> Self {
+        let pending_notes = Arc::new(PendingNotes {
+            notes: Mutex::new(BTreeMap::new()),
+            fire_note_off: Box::new(fire_note_off),
+        });
+        let (deadline_sender, deadline_receiver) = mpsc::channel();
+
+        let thread = {
+            let pending_notes = pending_notes.clone();
+            thread::spawn(move || run(deadline_receiver, &pending_notes))
+        };
+
+        Self {
+            pending_notes,
+            deadline_sender,
+            next_id: Cell::new(0),
+            thread,
+        }
+    }
+
+    pub fn hold(&self, note: Note) -> NoteOffId {
+        let id = self.next_id();
+        self.pending_notes.insert(id, note);
+        id
+    }
+
+    pub fn schedule(&self, note: Note, time: Instant) -> NoteOffId {
+        let id = self.hold(note);
+        self.deadline_sender.send(Reverse((time, id))).unwrap();
+        id
+    }
+
+    pub fn release(&self, id: NoteOffId) {
+        self.pending_notes.release(id);
+    }
+
+    // Returns whether the note off was still pending
+    pub fn cancel(&self, id: NoteOffId) -> bool {
+        self.pending_notes.cancel(id)
+    }
+
+    pub fn release_all(&self) {
+        self.pending_notes.release_all();
+    }
+
+    pub fn shutdown(self) {
+        drop(self.deadline_sender);
+        self.thread.join().unwrap();
+        self.pending_notes.release_all();
+    }
+
+    fn next_id(&self) -> NoteOffId {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        NoteOffId(id)
+    }
+}
+
+fn run(deadline_receiver: Receiver<Deadline>, pending_notes: &PendingNotes) {
+    let mut deadlines: BinaryHeap<Deadline> = BinaryHeap::new();
+
+    loop {
+        let new_deadline = match deadlines.peek() {
+            Some(Reverse((time, _))) => {
+                match deadline_receiver.recv_timeout(time.saturating_duration_since(Instant::now()))
+                {
+                    Ok(deadline) => Some(deadline),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match deadline_receiver.recv() {
+                Ok(deadline) => Some(deadline),
+                Err(_) => break,
+            },
+        };
+        deadlines.extend(new_deadline);
+
+        let now = Instant::now();
+        while let Some(Reverse((time, id))) = deadlines.peek().copied() {
+            if time > now {
+                break;
+            }
+            deadlines.pop();
+            pending_notes.release(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn scheduler() -> (NoteOffScheduler, Receiver<Note>) {
+        let (sender, receiver) = mpsc::channel();
+        (
+            NoteOffScheduler::new(move |note| sender.send(note).unwrap()),
+            receiver,
+        )
+    }
+
+    fn received(receiver: &Receiver<Note>, count: usize) -> Vec<Note> {
+        (0..count)
+            .map(|_| receiver.recv_timeout(Duration::from_secs(1)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn fires_in_deadline_order() {
+        let (scheduler, receiver) = scheduler();
+        let now = Instant::now();
+
+        scheduler.schedule(Note::C4, now + Duration::from_millis(60));
+        scheduler.schedule(Note::E4, now + Duration::from_millis(40));
+        scheduler.schedule(Note::G4, now + Duration::from_millis(20));
+
+        assert_eq!(received(&receiver, 3), vec![Note::G4, Note::E4, Note::C4]);
+    }
+
+    #[test]
+    fn wakes_early_for_sooner_events() {
+        let (scheduler, receiver) = scheduler();
+        let now = Instant::now();
+
+        scheduler.schedule(Note::C4, now + Duration::from_secs(10));
+        scheduler.schedule(Note::E4, now + Duration::from_millis(10));
+
+        assert_eq!(received(&receiver, 1), vec![Note::E4]);
+        assert!(now.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn release_fires_before_returning() {
+        let (scheduler, receiver) = scheduler();
+
+        let held = scheduler.hold(Note::E4);
+        let scheduled = scheduler.schedule(Note::C4, Instant::now() + Duration::from_secs(10));
+        scheduler.release(scheduled);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![Note::C4]);
+
+        scheduler.release(held);
+        scheduler.release(held);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![Note::E4]);
+    }
+
+    #[test]
+    fn cancelled_note_off_is_never_sent() {
+        let (scheduler, receiver) = scheduler();
+
+        let cancelled = scheduler.schedule(Note::C4, Instant::now() + Duration::from_millis(10));
+        assert!(scheduler.cancel(cancelled));
+        assert!(!scheduler.cancel(cancelled));
+        scheduler.release(cancelled);
+        scheduler.schedule(Note::E4, Instant::now() + Duration::from_millis(30));
+
+        assert_eq!(received(&receiver, 1), vec![Note::E4]);
+        scheduler.shutdown();
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![]);
+    }
+
+    #[test]
+    fn release_all_fires_before_returning() {
+        let (scheduler, receiver) = scheduler();
+
+        scheduler.hold(Note::E4);
+        scheduler.schedule(Note::C4, Instant::now() + Duration::from_secs(10));
+        scheduler.release_all();
+
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![Note::E4, Note::C4]
+        );
+    }
+
+    #[test]
+    fn shutdown_releases_pending_notes() {
+        let (scheduler, receiver) = scheduler();
+
+        scheduler.hold(Note::E4);
+        scheduler.schedule(Note::C4, Instant::now() + Duration::from_secs(10));
+        scheduler.shutdown();
+
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![Note::E4, Note::C4]
+        );
+    }
+}
@@ -1,10 +1,41 @@
+use std::time::Instant;
+use wmidi::Note;
+
+use super::note_off_scheduler::NoteOffId;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SoundingNote {
+    pub note_off_id: NoteOffId,
+    pub note: Note,
+    pub started: Instant,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum PlayingState {
     NotPlaying,
     Playing {
         line_index: usize,
         next_note_index: usize,
-        pitch_offset: i8,
-        has_fired_previous_note_off: bool,
+        sounding_note: Option<SoundingNote>,
+        ticks_since_start: u32,
     },
 }
+
+impl PlayingState {
+    pub fn next_tick(&self) -> Self {
+        match *self {
+            PlayingState::Playing {
+                line_index,
+                next_note_index,
+                sounding_note,
+                ticks_since_start,
+            } => PlayingState::Playing {
+                line_index,
+                next_note_index,
+                sounding_note,
+                ticks_since_start: ticks_since_start + 1,
+            },
+            PlayingState::NotPlaying => PlayingState::NotPlaying,
+        }
+    }
+}
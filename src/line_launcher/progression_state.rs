@@ -1,45 +1,190 @@
-use crate::{Chord, Progression};
+use crate::{Bar, BeatNumber, Chord, Meter, Progression};
 
-enum ProgressionChordIndexState {
+enum ProgressionBarIndexState {
     HaventStarted,
-    AtChordIndex(usize),
+    AtBarIndex(usize),
 }
 
-pub struct ProgressionState<'progression> {
-    progression: &'progression Progression,
-    chord_index_state: ProgressionChordIndexState,
+pub struct ProgressionState {
+    progression: Progression,
+    bar_index_state: ProgressionBarIndexState,
+    beat_number: BeatNumber,
 }
 
-impl<'progression> ProgressionState<'progression> {
-    pub fn new(progression: &'progression Progression) -> Self {
+impl ProgressionState {
+    pub fn new(progression: Progression) -> Self {
         Self {
             progression,
-            chord_index_state: ProgressionChordIndexState::HaventStarted,
+            bar_index_state: ProgressionBarIndexState::HaventStarted,
+            beat_number: BeatNumber::new(0, Meter::default().sixteenths_per_measure()),
         }
     }
 
-    pub fn chord_index(&self) -> usize {
-        if let ProgressionChordIndexState::AtChordIndex(chord_index) = self.chord_index_state {
-            chord_index
+    pub fn bar_index(&self) -> usize {
+        if let ProgressionBarIndexState::AtBarIndex(bar_index) = self.bar_index_state {
+            bar_index
         } else {
             0
         }
     }
 
+    pub fn current_bar(&self) -> &Bar {
+        &self.progression.bars[self.bar_index()]
+    }
+
     pub fn current_chord(&self) -> &Chord {
-        &self.progression.chords[self.chord_index()]
+        self.current_bar().chord_at(self.beat_number)
+    }
+
+    pub fn is_at_chord_change(&self) -> bool {
+        self.current_bar().chord_changes_at(self.beat_number)
+    }
+
+    pub fn set_progression(&mut self, progression: Progression) {
+        if let ProgressionBarIndexState::AtBarIndex(bar_index) = self.bar_index_state {
+            self.bar_index_state =
+                ProgressionBarIndexState::AtBarIndex(bar_index % progression.bars.len());
+        }
+        self.progression = progression;
+    }
+
+    pub fn reset(&mut self) {
+        self.bar_index_state = ProgressionBarIndexState::HaventStarted;
     }
 
-    pub fn tick_measure(&mut self) {
-        self.chord_index_state = match self.chord_index_state {
-            ProgressionChordIndexState::HaventStarted => {
-                ProgressionChordIndexState::AtChordIndex(0)
+    pub fn jump_to(&mut self, measure: u32, beat_number: BeatNumber) {
+        let bar_count = self.progression.bars.len();
+        let bar_index = measure as usize % bar_count;
+        // The next tick at the beginning of a measure advances the bar, so stay one bar behind
+        self.bar_index_state = if beat_number.is_beginning_of_measure() {
+            match bar_index {
+                0 => ProgressionBarIndexState::HaventStarted,
+                _ => ProgressionBarIndexState::AtBarIndex(bar_index - 1),
             }
-            ProgressionChordIndexState::AtChordIndex(chord_index) => {
-                ProgressionChordIndexState::AtChordIndex(
-                    (chord_index + 1) % self.progression.chords.len(),
-                )
+        } else {
+            ProgressionBarIndexState::AtBarIndex(bar_index)
+        };
+        self.beat_number = beat_number;
+    }
+
+    pub fn tick(&mut self, beat_number: BeatNumber) {
+        if beat_number.is_beginning_of_measure() {
+            self.tick_measure();
+        }
+        self.beat_number = beat_number;
+    }
+
+    fn tick_measure(&mut self) {
+        self.bar_index_state = match self.bar_index_state {
+            ProgressionBarIndexState::HaventStarted => ProgressionBarIndexState::AtBarIndex(0),
+            ProgressionBarIndexState::AtBarIndex(bar_index) => {
+                ProgressionBarIndexState::AtBarIndex((bar_index + 1) % self.progression.bars.len())
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticked_chords(
+        progression: &str,
+        sixteenths_per_measure: u32,
+        num_sixteenths: u32,
+    ) -> Vec<(String, bool)> {
+        let mut progression_state = ProgressionState::new(Progression::parse(progression).unwrap());
+        (0..num_sixteenths)
+            .map(|sixteenth_note| {
+                progression_state.tick(BeatNumber::new(
+                    sixteenth_note % sixteenths_per_measure,
+                    sixteenths_per_measure,
+                ));
+                (
+                    progression_state.current_chord().to_string(),
+                    progression_state.is_at_chord_change(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn advances_one_chord_per_bar() {
+        let chords = ticked_chords("C F", 16, 48);
+
+        assert_eq!(chords[0], ("C".to_string(), true));
+        assert_eq!(chords[15], ("C".to_string(), false));
+        assert_eq!(chords[16], ("F".to_string(), true));
+        assert_eq!(chords[32], ("C".to_string(), true));
+    }
+
+    #[test]
+    fn advances_within_bar() {
+        let chords = ticked_chords("| Dm7 G7 | CM7 . . . |", 16, 32);
+
+        assert_eq!(chords[0], ("Dm7".to_string(), true));
+        assert_eq!(chords[7], ("Dm7".to_string(), false));
+        assert_eq!(chords[8], ("G7".to_string(), true));
+        assert_eq!(chords[16], ("CM7".to_string(), true));
+        assert_eq!(chords[20], ("CM7".to_string(), false));
+    }
+
+    #[test]
+    fn advances_in_three_four() {
+        let chords = ticked_chords("| C . G7 | F |", 12, 24);
+
+        assert_eq!(chords[0], ("C".to_string(), true));
+        assert_eq!(chords[7], ("C".to_string(), false));
+        assert_eq!(chords[8], ("G7".to_string(), true));
+        assert_eq!(chords[12], ("F".to_string(), true));
+    }
+
+    #[test]
+    fn reset_returns_to_first_bar() {
+        let progression = Progression::parse("C F G").unwrap();
+        let mut progression_state = ProgressionState::new(progression);
+        for sixteenth_note in 0..40 {
+            progression_state.tick(BeatNumber::new(sixteenth_note % 16, 16));
+        }
+        assert_eq!(progression_state.current_chord().to_string(), "G");
+
+        progression_state.reset();
+        progression_state.tick(BeatNumber::new(0, 16));
+
+        assert_eq!(progression_state.current_chord().to_string(), "C");
+        assert!(progression_state.is_at_chord_change());
+    }
+
+    #[test]
+    fn jump_to_measure() {
+        let progression = Progression::parse("| C | F | G7 . Am . |").unwrap();
+        let mut progression_state = ProgressionState::new(progression);
+
+        progression_state.jump_to(4, BeatNumber::new(0, 16));
+        progression_state.tick(BeatNumber::new(0, 16));
+        assert_eq!(progression_state.current_chord().to_string(), "F");
+        assert!(progression_state.is_at_chord_change());
+
+        progression_state.jump_to(2, BeatNumber::new(9, 16));
+        progression_state.tick(BeatNumber::new(9, 16));
+        assert_eq!(progression_state.current_chord().to_string(), "Am");
+
+        progression_state.jump_to(3, BeatNumber::new(0, 16));
+        progression_state.tick(BeatNumber::new(0, 16));
+        assert_eq!(progression_state.current_chord().to_string(), "C");
+    }
+
+    #[test]
+    fn set_progression_keeps_going_from_the_current_bar() {
+        let mut progression_state = ProgressionState::new(Progression::parse("C F G").unwrap());
+        for sixteenth_note in 0..40 {
+            progression_state.tick(BeatNumber::new(sixteenth_note % 16, 16));
+        }
+
+        progression_state.set_progression(Progression::parse("Dm7 G7").unwrap());
+        progression_state.tick(BeatNumber::new(0, 16));
+
+        assert_eq!(progression_state.current_chord().to_string(), "G7");
+        assert!(progression_state.is_at_chord_change());
+    }
+}
@@ -1,84 +1,331 @@
-use midir::os::unix::{VirtualInput, VirtualOutput};
-use midir::{MidiInput, MidiOutput};
-use std::env;
+use anyhow::anyhow;
+use clap::{Args, Parser, Subcommand};
 use std::fs;
-use wmidi::MidiMessage;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use wmidi::Channel;
 
-use line_runner::{config, midi, Config, LineLauncher, Message, MidiClockTracker, Result};
+use line_runner::{
+    import, midi, render, Config, Line, Meter, MidiClockTracker, MidiControls, Pitch, Progression,
+    Result,
+};
+
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Plays lines over chord progressions, following a MIDI clock"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play along with the clock
+    Run(ConfigArgs),
+    /// List the available MIDI input and output ports
+    ListPorts,
+    /// Check that a config file and the lines it refers to are valid
+    Validate { config: PathBuf },
+    /// Render the progression to a Standard MIDI File
+    Render {
+        #[command(flatten)]
+        config: ConfigArgs,
+        /// The MIDI file to write
+        #[arg(short, long)]
+        output: PathBuf,
+        /// How many times to play through the progression
+        #[arg(long, default_value_t = 1)]
+        choruses: u32,
+    },
+    /// Print the lines that would be played, in the line notation
+    ListLines(ConfigArgs),
+    /// Print the progression that would be played
+    ShowProgression(ConfigArgs),
+    /// Print the lines in a MIDI file in the line notation
+    Import {
+        midi_file: PathBuf,
+        /// The root the lines are played against (they're transposed so that it becomes C)
+        #[arg(long, value_parser = Pitch::parse)]
+        root: Pitch,
+    },
+}
+
+#[derive(Args)]
+struct ConfigArgs {
+    /// The YAML config file (defaults are used without one)
+    config: Option<PathBuf>,
+    /// The MIDI input port to listen to for control changes
+    #[arg(long)]
+    port: Option<String>,
+    /// The MIDI output port to play to (defaults to a virtual port)
+    #[arg(long)]
+    output_port: Option<String>,
+    /// The MIDI channel to play on
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=16))]
+    channel: Option<u8>,
+    /// Makes the choice of lines repeatable: the same seed always gives the same lines
+    #[arg(long)]
+    seed: Option<u64>,
+    /// The tempo of the internal clock and of rendered files
+    #[arg(long)]
+    bpm: Option<f64>,
+}
+
+impl ConfigArgs {
+    fn config(&self) -> Result<Config> {
+        let mut config = self
+            .config
+            .as_deref()
+            .map(config_from_path)
+            .transpose()?
+            .unwrap_or_default();
+        if let Some(port) = &self.port {
+            config.midi.port = Some(port.clone());
+        }
+        if let Some(output_port) = &self.output_port {
+            config.midi.output_port = Some(output_port.clone());
+        }
+        if let Some(channel) = self.channel {
+            config.midi.output_channel = Some(Channel::from_index(channel - 1)?);
+        }
+        if let Some(seed) = self.seed {
+            config.seed = Some(seed);
+        }
+        if let Some(bpm) = self.bpm {
+            if !(bpm > 0.0 && bpm.is_finite()) {
+                return Err(anyhow!("bpm must be a positive number, got {}", bpm));
+            }
+            config.clock.bpm = bpm;
+        }
+        Ok(config)
+    }
+}
 
 fn main() -> Result<()> {
-    let config = get_config()?;
+    match Cli::parse().command {
+        Command::Run(config_args) => play(&config_args.config()?, config_args.config.as_deref()),
+        Command::ListPorts => list_ports(),
+        Command::Validate { config } => validate(&config),
+        Command::Render {
+            config,
+            output,
+            choruses,
+        } => render(&config.config()?, &output, choruses),
+        Command::ListLines(config_args) => list_lines(&config_args.config()?),
+        Command::ShowProgression(config_args) => show_progression(&config_args.config()?),
+        Command::Import { midi_file, root } => print_imported_lines(&midi_file, root),
+    }
+}
 
-    let midi_out = MidiOutput::new("Line runner").unwrap();
+fn list_ports() -> Result<()> {
+    println!("MIDI input ports:");
+    for port_name in midi::port_names()? {
+        println!("  {}", port_name);
+    }
+    println!("MIDI output ports:");
+    for port_name in midi::output_port_names()? {
+        println!("  {}", port_name);
+    }
 
-    let conn_out = midi_out.create_virtual("Line runner").unwrap();
+    Ok(())
+}
 
-    let midi_in = MidiInput::new("Line runner").unwrap();
+fn validate(path: &Path) -> Result<()> {
+    let config = config_from_path(path)?;
+    config.validate()?;
+    config.validate_ports(&midi::port_names()?, &midi::output_port_names()?)?;
 
-    let (mut midi_clock_tracker, beat_message_receiver) = MidiClockTracker::new();
+    println!(
+        "Config is valid: {} bar(s) in {}, {} line(s)",
+        config.progression.bars.len(),
+        config.meter(),
+        config.lines()?.len()
+    );
 
-    let _conn_in = midi_in
-        .create_virtual(
-            "Line runner",
-            move |timestamp, bytes, _| {
-                if let Some(message) = Message::from(timestamp, bytes).unwrap() {
-                    handle_message(message, &mut midi_clock_tracker);
-                }
-            },
-            (),
-        )
-        .unwrap();
+    Ok(())
+}
+
+fn list_lines(config: &Config) -> Result<()> {
+    for line in config.lines()? {
+        print_line(&line);
+    }
+
+    Ok(())
+}
+
+fn show_progression(config: &Config) -> Result<()> {
+    println!("{}", config.progression.clone().with_meter(config.meter()));
+
+    Ok(())
+}
+
+fn print_imported_lines(path: &Path, root: Pitch) -> Result<()> {
+    for line in import::lines_from_smf_file(path, root)? {
+        print_line(&line);
+    }
+
+    Ok(())
+}
+
+fn print_line(line: &Line) {
+    match line.to_notation() {
+        Some(notation) => println!("{}", notation),
+        None => println!("# {:?}", line),
+    }
+}
+
+fn render(config: &Config, path: &Path, choruses: u32) -> Result<()> {
+    config.validate()?;
+    render::render_to_file(
+        &config.line_launcher()?,
+        config.meter(),
+        config.clock.bpm,
+        choruses,
+        path,
+    )?;
+    eprintln!("Rendered {} chorus(es) to {}", choruses, path.display());
+
+    Ok(())
+}
+
+fn play(config: &Config, config_path: Option<&Path>) -> Result<()> {
+    let reload_receiver = match config_path {
+        Some(config_path) => watch_config(config_path.to_path_buf(), config),
+        None => mpsc::channel().1,
+    };
 
     let midi_port_names = midi::port_names()?;
+    let output_port_names = midi::output_port_names()?;
+    config.validate()?;
+    config.validate_ports(&midi_port_names, &output_port_names)?;
+
+    let conn_out = midi::connect_output(config.midi.output_port.as_deref())?;
+
+    let (midi_clock_tracker, clock_message_receiver) = MidiClockTracker::new(config.meter());
+
+    let _clock = config.clock.to_clock().start(midi_clock_tracker)?;
 
     if config.midi.port.is_none() && !midi_port_names.is_empty() {
-        println!(
+        eprintln!(
             "Config is missing 'midi.port'. Available MIDI ports are:\n{}",
             midi_port_names.join("\n")
         );
     }
 
+    if config.midi.output_port.is_none() && !output_port_names.is_empty() {
+        eprintln!(
+            "Config is missing 'midi.output_port', using a virtual port. Available MIDI output ports are:\n{}",
+            output_port_names.join("\n")
+        );
+    }
+
     let midi_messages = match &config.midi.port {
         Some(port_name) => Some(midi::listen_for_input(port_name)?),
         None => None,
     };
 
-    let Config {
-        progression,
-        midi: config::midi::Midi {
-            duration_ratio_slider,
-            ..
-        },
-        ..
-    } = config;
-    let line_launcher = LineLauncher::from(progression);
+    let line_launcher = config.line_launcher()?;
+    if let Some(seed) = config.seed {
+        eprintln!("Seed: {}", seed);
+    }
+
+    let (shutdown_sender, shutdown_receiver) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = shutdown_sender.send(());
+    })?;
+
     line_launcher.listen(
-        beat_message_receiver,
+        clock_message_receiver,
         conn_out,
-        midi_messages,
-        duration_ratio_slider,
+        MidiControls {
+            midi_messages,
+            duration_ratio_slider: config.midi.duration_ratio_slider,
+            panic_trigger: config.midi.panic_trigger,
+        },
+        reload_receiver,
+        shutdown_receiver,
     );
 
     Ok(())
 }
 
-fn handle_message(message: Message, midi_clock_tracker: &mut MidiClockTracker) {
-    if message.message == MidiMessage::TimingClock {
-        midi_clock_tracker.tick();
-    }
+fn watch_config(path: PathBuf, config: &Config) -> Receiver<(Progression, Vec<Line>)> {
+    let (sender, receiver) = mpsc::channel();
+    let meter = config.meter();
+    let mut paths = watched_paths(&path, config);
+    let mut last_modified = modified_times(&paths);
+
+    thread::spawn(move || loop {
+        thread::sleep(CONFIG_POLL_INTERVAL);
+        let current_modified = modified_times(&paths);
+        // A file that's missing is probably being saved, so wait for it to come back
+        if current_modified.contains(&None) || current_modified == last_modified {
+            continue;
+        }
+        last_modified = current_modified;
+
+        match reload_config(&path, meter) {
+            Ok((config, lines)) => {
+                eprintln!("Config changed, switching at the next bar line");
+                paths = watched_paths(&path, &config);
+                last_modified = modified_times(&paths);
+                if sender.send((config.progression, lines)).is_err() {
+                    break;
+                }
+            }
+            Err(err) => eprintln!("Keeping the previous config: {}", err),
+        }
+    });
+
+    receiver
+}
+
+fn watched_paths(config_path: &Path, config: &Config) -> Vec<PathBuf> {
+    let mut paths = vec![config_path.to_path_buf()];
+    paths.extend(config.lines_file.clone());
+    paths.extend(
+        config
+            .lines_midi_files
+            .iter()
+            .map(|lines_midi_file| lines_midi_file.path.clone()),
+    );
+    paths
 }
 
-fn get_config() -> Result<Config> {
-    let config = env::args()
-        .nth(1)
-        .map(|path| config_from_path(&path))
-        .transpose()?;
-    Ok(config.unwrap_or_default())
+fn modified_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+fn reload_config(path: &Path, meter: Meter) -> Result<(Config, Vec<Line>)> {
+    let config = config_from_path(path)?;
+    config.validate()?;
+    if config.meter() != meter {
+        return Err(anyhow!(
+            "changing the time signature from {} to {} needs a restart",
+            meter,
+            config.meter()
+        ));
+    }
+
+    let lines = config.lines()?;
+    Ok((config, lines))
 }
 
-fn config_from_path(path: &str) -> Result<Config> {
-    println!("Reading config from {}", path);
+fn config_from_path(path: &Path) -> Result<Config> {
+    eprintln!("Reading config from {}", path.display());
 
-    let contents = fs::read_to_string(path)?;
-    Config::from(&contents)
+    Config::from_file(path)
 }
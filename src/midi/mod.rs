@@ -1,8 +1,10 @@
 pub mod message;
+pub mod sink;
 
 use crate::{Message, MidiSlider, Result};
 use anyhow::anyhow;
-use midir::{MidiInput, MidiInputPort};
+use midir::os::unix::VirtualOutput;
+use midir::{MidiInput, MidiInputPort, MidiOutput, MidiOutputConnection};
 use num_traits::Num;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::thread;
@@ -12,7 +14,7 @@ use wmidi::{ControlValue, MidiMessage};
 pub fn listen_for_input(port_name: &str) -> Result<Receiver<Message>> {
     let port = port(port_name)?;
 
-    println!("MIDI input: {}", port_name);
+    eprintln!("MIDI input: {}", port_name);
 
     let (sender, receiver) = mpsc::channel();
     handle_messages(port, sender);
@@ -29,6 +31,45 @@ pub fn port_names() -> Result<Vec<String>> {
         .collect()
 }
 
+pub fn connect_output(port_name: Option<&str>) -> Result<MidiOutputConnection> {
+    let midi_output = midi_output()?;
+
+    match port_name {
+        Some(port_name) => {
+            let names = output_port_names()?;
+            let port = midi_output
+                .ports()
+                .into_iter()
+                .find(|port| midi_output.port_name(port) == Ok(port_name.into()))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Could not find a MIDI output port with name '{}'. Available output ports are:\n{}",
+                        port_name,
+                        names.join("\n")
+                    )
+                })?;
+
+            eprintln!("MIDI output: {}", port_name);
+
+            midi_output
+                .connect(&port, "Line runner")
+                .map_err(|err| anyhow!("Could not connect to MIDI output '{}': {}", port_name, err))
+        }
+        None => midi_output
+            .create_virtual("Line runner")
+            .map_err(|err| anyhow!("Could not create virtual MIDI output: {}", err)),
+    }
+}
+
+pub fn output_port_names() -> Result<Vec<String>> {
+    let midi_output = midi_output()?;
+    midi_output
+        .ports()
+        .iter()
+        .map(|port| midi_output.port_name(port).map_err(|err| err.into()))
+        .collect()
+}
+
 pub fn get_control_value(slider: MidiSlider, message: &Message) -> Option<ControlValue> {
     match message.message {
         MidiMessage::ControlChange(channel, function, value)
@@ -56,6 +97,10 @@ fn midi_input() -> Result<MidiInput> {
     Ok(MidiInput::new("Input")?)
 }
 
+fn midi_output() -> Result<MidiOutput> {
+    Ok(MidiOutput::new("Line runner")?)
+}
+
 fn port(name: &str) -> Result<MidiInputPort> {
     let names = port_names()?;
     let midi_input = midi_input()?;
@@ -63,7 +108,7 @@ fn port(name: &str) -> Result<MidiInputPort> {
     midi_input
         .ports()
         .into_iter()
-        .find(|port| midi_input.port_name(&port) == Ok(name.into()))
+        .find(|port| midi_input.port_name(port) == Ok(name.into()))
         .ok_or_else(|| {
             anyhow!(
                 "Could not find a MIDI port with name '{}'. Available ports are:\n{}",
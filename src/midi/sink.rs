@@ -0,0 +1,51 @@
+use crate::Result;
+use midir::MidiOutputConnection;
+use std::sync::{Arc, Mutex};
+use wmidi::MidiMessage;
+
+pub trait MidiSink: Send {
+    fn send(&mut self, midi_message: &MidiMessage) -> Result<()>;
+}
+
+impl MidiSink for MidiOutputConnection {
+    fn send(&mut self, midi_message: &MidiMessage) -> Result<()> {
+        let mut bytes_buffer = vec![0; midi_message.bytes_size()];
+        midi_message.copy_to_slice(&mut bytes_buffer)?;
+        MidiOutputConnection::send(self, &bytes_buffer)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RecordingSink {
+    midi_messages: Arc<Mutex<Vec<MidiMessage<'static>>>>,
+}
+
+impl RecordingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn midi_messages(&self) -> Vec<MidiMessage<'static>> {
+        self.midi_messages.lock().unwrap().clone()
+    }
+}
+
+impl MidiSink for RecordingSink {
+    fn send(&mut self, midi_message: &MidiMessage) -> Result<()> {
+        self.midi_messages
+            .lock()
+            .unwrap()
+            .push(midi_message.to_owned());
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullSink;
+
+impl MidiSink for NullSink {
+    fn send(&mut self, _midi_message: &MidiMessage) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,51 +1,221 @@
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Instant;
 
-use crate::BeatNumber;
+use crate::{beat_number::TICKS_PER_SIXTEENTH_NOTE, BeatNumber, Meter, Result};
 
-const TICKS_PER_QUARTER_NOTE: u32 = 24;
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClockMessage {
+    Beat(BeatNumber, Instant),
+    Start,
+    Stop,
+    Continue,
+    SongPosition {
+        measure: u32,
+        beat_number: BeatNumber,
+    },
+}
 
 pub struct MidiClockTracker {
     ticks_received: u32,
-    sender: Sender<BeatNumber>,
+    is_running: bool,
+    meter: Meter,
+    sender: Sender<ClockMessage>,
 }
 
 impl MidiClockTracker {
-    pub fn new() -> (Self, Receiver<BeatNumber>) {
+    pub fn new(meter: Meter) -> (Self, Receiver<ClockMessage>) {
         let (sender, receiver) = mpsc::channel();
 
         (
             Self {
                 ticks_received: 0,
+                is_running: true,
+                meter,
                 sender,
             },
             receiver,
         )
     }
 
-    pub fn tick(&mut self) {
+    pub fn tick(&mut self) -> Result<()> {
+        self.tick_at(Instant::now())
+    }
+
+    pub fn tick_at(&mut self, at: Instant) -> Result<()> {
+        if !self.is_running {
+            return Ok(());
+        }
         self.ticks_received += 1;
-        self.emit_beat_number();
+        self.emit_beat_number(at)
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        self.ticks_received = 0;
+        self.is_running = true;
+        self.sender.send(ClockMessage::Start)?;
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        self.is_running = false;
+        self.sender.send(ClockMessage::Stop)?;
+        Ok(())
+    }
+
+    pub fn resume(&mut self) -> Result<()> {
+        self.is_running = true;
+        self.sender.send(ClockMessage::Continue)?;
+        Ok(())
+    }
+
+    pub fn set_song_position(&mut self, sixteenths_since_start: u32) -> Result<()> {
+        self.ticks_received = sixteenths_since_start * TICKS_PER_SIXTEENTH_NOTE;
+
+        let sixteenths_per_measure = self.meter.sixteenths_per_measure();
+
+        self.sender.send(ClockMessage::SongPosition {
+            measure: sixteenths_since_start / sixteenths_per_measure,
+            beat_number: BeatNumber::new(
+                sixteenths_since_start % sixteenths_per_measure,
+                sixteenths_per_measure,
+            ),
+        })?;
+        Ok(())
     }
 
-    fn emit_beat_number(&self) {
+    fn emit_beat_number(&self, at: Instant) -> Result<()> {
         let use_ticks_received = self.ticks_received - 1;
 
-        if use_ticks_received % (TICKS_PER_QUARTER_NOTE / 4) != 0 {
-            return;
-        }
+        let sixteenths_per_measure = self.meter.sixteenths_per_measure();
 
-        let ticks_this_measure = use_ticks_received % (TICKS_PER_QUARTER_NOTE * 4);
+        let ticks_this_measure =
+            use_ticks_received % (sixteenths_per_measure * TICKS_PER_SIXTEENTH_NOTE);
 
-        let quarter_note = ticks_this_measure / TICKS_PER_QUARTER_NOTE;
+        self.sender.send(ClockMessage::Beat(
+            BeatNumber::from_ticks(ticks_this_measure, sixteenths_per_measure),
+            at,
+        ))?;
+        Ok(())
+    }
+}
 
-        let ticks_this_quarter_note = ticks_this_measure % TICKS_PER_QUARTER_NOTE;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let sixteenth_note = ticks_this_quarter_note / (TICKS_PER_QUARTER_NOTE / 4);
+    fn emitted_beat_numbers(meter: Meter, num_ticks: u32) -> Vec<BeatNumber> {
+        let (mut midi_clock_tracker, receiver) = MidiClockTracker::new(meter);
+        for _ in 0..num_ticks {
+            midi_clock_tracker.tick().unwrap();
+        }
+        beat_numbers(&receiver)
+    }
 
-        self.sender
-            .send(BeatNumber {
-                sixteenth_note: quarter_note * 4 + sixteenth_note,
+    fn beat_numbers(receiver: &Receiver<ClockMessage>) -> Vec<BeatNumber> {
+        receiver
+            .try_iter()
+            .filter_map(|message| match message {
+                ClockMessage::Beat(beat_number, _) => Some(beat_number),
+                _ => None,
             })
-            .unwrap();
+            .collect()
+    }
+
+    fn emitted_sixteenth_notes(meter: Meter, num_ticks: u32) -> Vec<BeatNumber> {
+        emitted_beat_numbers(meter, num_ticks)
+            .into_iter()
+            .filter(BeatNumber::is_on_sixteenth_note)
+            .collect()
+    }
+
+    #[test]
+    fn emits_every_tick() {
+        let beat_numbers = emitted_beat_numbers(Meter::new(4, 4), 8);
+
+        assert_eq!(beat_numbers.len(), 8);
+        assert_eq!(beat_numbers[5], BeatNumber::from_ticks(5, 16));
+        assert_eq!(beat_numbers[7], BeatNumber::new(1, 16).with_tick(1));
+    }
+
+    #[test]
+    fn emits_sixteenths_in_four_four() {
+        let beat_numbers = emitted_sixteenth_notes(Meter::new(4, 4), 24 * 4 + 1);
+
+        assert_eq!(beat_numbers.len(), 17);
+        assert_eq!(beat_numbers[1], BeatNumber::new(1, 16));
+        assert_eq!(beat_numbers[15], BeatNumber::new(15, 16));
+        assert_eq!(beat_numbers[16], BeatNumber::new(0, 16));
+    }
+
+    #[test]
+    fn emits_sixteenths_in_seven_eight() {
+        let beat_numbers = emitted_sixteenth_notes(Meter::new(7, 8), 12 * 7 + 1);
+
+        assert_eq!(beat_numbers.len(), 15);
+        assert_eq!(beat_numbers[13], BeatNumber::new(13, 14));
+        assert_eq!(beat_numbers[14], BeatNumber::new(0, 14));
+    }
+
+    #[test]
+    fn start_resets_to_beginning_of_measure() {
+        let (mut midi_clock_tracker, receiver) = MidiClockTracker::new(Meter::new(4, 4));
+        for _ in 0..10 {
+            midi_clock_tracker.tick().unwrap();
+        }
+        receiver.try_iter().for_each(drop);
+
+        let now = Instant::now();
+        midi_clock_tracker.start().unwrap();
+        midi_clock_tracker.tick_at(now).unwrap();
+
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                ClockMessage::Start,
+                ClockMessage::Beat(BeatNumber::new(0, 16), now)
+            ]
+        );
+    }
+
+    #[test]
+    fn stop_pauses_until_continue() {
+        let (mut midi_clock_tracker, receiver) = MidiClockTracker::new(Meter::new(4, 4));
+        for _ in 0..6 {
+            midi_clock_tracker.tick().unwrap();
+        }
+        midi_clock_tracker.stop().unwrap();
+        for _ in 0..6 {
+            midi_clock_tracker.tick().unwrap();
+        }
+        midi_clock_tracker.resume().unwrap();
+        midi_clock_tracker.tick().unwrap();
+
+        let beat_numbers = beat_numbers(&receiver);
+
+        assert_eq!(beat_numbers.len(), 7);
+        assert_eq!(beat_numbers[6], BeatNumber::new(1, 16));
+    }
+
+    #[test]
+    fn song_position_moves_ticks() {
+        let (mut midi_clock_tracker, receiver) = MidiClockTracker::new(Meter::new(3, 4));
+        midi_clock_tracker.stop().unwrap();
+        midi_clock_tracker.set_song_position(12 * 5 + 4).unwrap();
+        midi_clock_tracker.resume().unwrap();
+        let now = Instant::now();
+        midi_clock_tracker.tick_at(now).unwrap();
+
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                ClockMessage::Stop,
+                ClockMessage::SongPosition {
+                    measure: 5,
+                    beat_number: BeatNumber::new(4, 12)
+                },
+                ClockMessage::Continue,
+                ClockMessage::Beat(BeatNumber::new(4, 12), now)
+            ]
+        );
     }
 }
@@ -0,0 +1,95 @@
+use crate::{BeatNumber, Chord};
+use std::fmt;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Bar {
+    pub chords: Vec<Chord>,
+}
+
+impl Bar {
+    pub fn new(chords: Vec<Chord>) -> Self {
+        Self { chords }
+    }
+
+    pub fn chord_at(&self, beat_number: BeatNumber) -> &Chord {
+        &self.chords[self.slot_index(beat_number)]
+    }
+
+    pub fn chord_changes_at(&self, beat_number: BeatNumber) -> bool {
+        if !beat_number.is_on_sixteenth_note() {
+            return false;
+        }
+        if beat_number.is_beginning_of_measure() {
+            return true;
+        }
+        let slot_index = self.slot_index(beat_number);
+        let previous_slot_index = self.slot_index(beat_number.minus_sixteenths(1));
+        slot_index != previous_slot_index
+            && self.chords[slot_index] != self.chords[previous_slot_index]
+    }
+
+    fn slot_index(&self, beat_number: BeatNumber) -> usize {
+        beat_number.sixteenth_note as usize * self.chords.len()
+            / beat_number.sixteenths_per_measure as usize
+    }
+}
+
+impl fmt::Display for Bar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let string = self
+            .chords
+            .iter()
+            .enumerate()
+            .map(|(index, chord)| {
+                if index > 0 && self.chords[index - 1] == *chord {
+                    ".".to_string()
+                } else {
+                    chord.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        f.write_str(&string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use combine::Parser;
+
+    fn chord(string: &str) -> Chord {
+        Chord::parser::<&str>().parse(string).unwrap().0
+    }
+
+    #[test]
+    fn chord_at_splits_bar_evenly() {
+        let bar = Bar::new(vec![chord("Dm7"), chord("G7")]);
+
+        assert_eq!(bar.chord_at(BeatNumber::new(0, 16)), &chord("Dm7"));
+        assert_eq!(bar.chord_at(BeatNumber::new(7, 16)), &chord("Dm7"));
+        assert_eq!(bar.chord_at(BeatNumber::new(8, 16)), &chord("G7"));
+        assert_eq!(bar.chord_at(BeatNumber::new(15, 16)), &chord("G7"));
+    }
+
+    #[test]
+    fn chord_changes_at() {
+        let bar = Bar::new(vec![chord("C"), chord("C"), chord("A7"), chord("A7")]);
+
+        assert!(bar.chord_changes_at(BeatNumber::new(0, 16)));
+        assert!(!bar.chord_changes_at(BeatNumber::new(4, 16)));
+        assert!(bar.chord_changes_at(BeatNumber::new(8, 16)));
+        assert!(!bar.chord_changes_at(BeatNumber::new(9, 16)));
+        assert!(!bar.chord_changes_at(BeatNumber::new(12, 16)));
+        assert!(!bar.chord_changes_at(BeatNumber::new(8, 16).with_tick(1)));
+    }
+
+    #[test]
+    fn chord_at_three_four() {
+        let bar = Bar::new(vec![chord("C"), chord("C"), chord("G7")]);
+
+        assert_eq!(bar.chord_at(BeatNumber::new(7, 12)), &chord("C"));
+        assert_eq!(bar.chord_at(BeatNumber::new(8, 12)), &chord("G7"));
+        assert!(bar.chord_changes_at(BeatNumber::new(8, 12)));
+    }
+}
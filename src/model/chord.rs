@@ -1,35 +1,143 @@
-use crate::{Pitch, Quality};
-use combine::{Parser, Stream};
+use crate::{ChordFamily, Letter, Modifier, Pitch, Quality};
+use combine::{
+    attempt, choice, error::StreamError, optional, parser::char::string, satisfy,
+    stream::StreamErrorFor, token, Parser, Stream,
+};
 use std::fmt;
 
+// Tried in order, so numerals come before any numeral that is a prefix of them
+const ROMAN_NUMERALS: [(&str, i8); 14] = [
+    ("VII", 7),
+    ("III", 3),
+    ("II", 2),
+    ("IV", 4),
+    ("VI", 6),
+    ("V", 5),
+    ("I", 1),
+    ("vii", -7),
+    ("iii", -3),
+    ("ii", -2),
+    ("iv", -4),
+    ("vi", -6),
+    ("v", -5),
+    ("i", -1),
+];
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Chord {
     pub pitch: Pitch,
     pub quality: Quality,
+    pub bass: Option<Pitch>,
 }
 
 impl Chord {
     pub fn new(pitch: Pitch, quality: Quality) -> Self {
-        Self { pitch, quality }
+        Self {
+            pitch,
+            quality,
+            bass: None,
+        }
+    }
+
+    pub fn with_bass(self, bass: Pitch) -> Self {
+        Self {
+            bass: Some(bass),
+            ..self
+        }
     }
 
     pub fn parser<Input>() -> impl Parser<Input, Output = Self>
     where
         Input: Stream<Token = char>,
     {
-        (Pitch::parser(), Quality::parser()).map(|(pitch, quality)| Chord::new(pitch, quality))
+        let bass_parser = (token('/'), Pitch::parser()).map(|(_, bass)| bass);
+
+        (Pitch::parser(), Quality::parser(), optional(bass_parser)).map(|(pitch, quality, bass)| {
+            let chord = Chord::new(pitch, quality);
+            match bass {
+                Some(bass) => chord.with_bass(bass),
+                None => chord,
+            }
+        })
+    }
+
+    // Parses Roman numerals (ii7, bVII) and Nashville numbers (2m7, b7) as chords in C
+    pub fn numeral_parser<Input>() -> impl Parser<Input, Output = Self>
+    where
+        Input: Stream<Token = char>,
+    {
+        let roman_numeral_parser = choice(
+            ROMAN_NUMERALS.map(|(numeral, degree)| attempt(string(numeral)).map(move |_| degree)),
+        );
+        let nashville_number_parser =
+            satisfy(|c: char| ('1'..='7').contains(&c)).map(|c: char| c as i8 - '0' as i8);
+
+        (
+            Modifier::parser(),
+            choice((roman_numeral_parser, nashville_number_parser)),
+            Quality::parser(),
+        )
+            .and_then(|(modifier, degree, quality)| {
+                let pitch = Pitch::new(Letter::from_steps_from_c(degree.abs() - 1), modifier);
+                // Lower case Roman numerals are minor
+                let quality = if degree < 0 {
+                    minor_quality(quality).ok_or_else(|| {
+                        StreamErrorFor::<Input>::message_static_message(
+                            "lower case numerals are minor chords, this quality has no minor form",
+                        )
+                    })?
+                } else {
+                    quality
+                };
+                Ok::<_, StreamErrorFor<Input>>(Chord::new(pitch, quality))
+            })
+    }
+
+    pub fn transposed_to(&self, key: Pitch) -> Self {
+        Self {
+            pitch: self.pitch.transposed_to(key),
+            bass: self.bass.map(|bass| bass.transposed_to(key)),
+            ..*self
+        }
+    }
+
+    pub fn bass_or_root(&self) -> Pitch {
+        self.bass.unwrap_or(self.pitch)
+    }
+}
+
+fn minor_quality(quality: Quality) -> Option<Quality> {
+    match quality {
+        Quality::Major => Some(Quality::Minor),
+        Quality::Sixth => Some(Quality::MinorSixth),
+        Quality::Seventh => Some(Quality::MinorSeventh),
+        Quality::MajorSeventh => Some(Quality::MinorMajorSeventh),
+        Quality::Ninth => Some(Quality::MinorNinth),
+        Quality::Eleventh => Some(Quality::MinorEleventh),
+        Quality::Thirteenth => Some(Quality::MinorThirteenth),
+        quality => match quality.family() {
+            ChordFamily::Minor | ChordFamily::Diminished | ChordFamily::HalfDiminished => {
+                Some(quality)
+            }
+            _ => None,
+        },
     }
 }
 
 impl fmt::Display for Chord {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&format!("{}{}", self.pitch, self.quality))
+        f.write_str(&format!("{}{}", self.pitch, self.quality))?;
+        if let Some(bass) = self.bass {
+            f.write_str(&format!("/{}", bass))?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Letter, Modifier};
     use strum::IntoEnumIterator;
 
     #[test]
@@ -46,4 +154,107 @@ mod tests {
 
         assert_eq!(parsed, chords);
     }
+
+    #[test]
+    fn parser_lead_sheet_symbols() {
+        let chords = vec![
+            ("Bbmaj7", "BbM7"),
+            ("F#ø7", "F#m7b5"),
+            ("C#°7", "C#dim7"),
+            ("Eb-7", "Ebm7"),
+            ("G7b9", "G7b9"),
+            ("Ab7#11", "Ab7#11"),
+            ("D7sus", "D7sus4"),
+            ("E+", "Eaug"),
+            ("AmM7", "AmM7"),
+            ("Cmaj", "C"),
+            ("CM", "C"),
+            ("CΔ", "CM7"),
+            ("Cm13", "Cm13"),
+            ("C11", "C11"),
+        ];
+
+        for (string, normalized) in chords {
+            assert_eq!(
+                Chord::parser::<&str>().parse(string).unwrap().0.to_string(),
+                normalized
+            );
+        }
+    }
+
+    #[test]
+    fn parser_slash_chords() {
+        let chords: Vec<_> = Pitch::all()
+            .map(|bass| {
+                Chord::new(Pitch::new(Letter::C, Modifier::Natural), Quality::Major).with_bass(bass)
+            })
+            .chain(
+                Pitch::all().map(|pitch| Chord::new(pitch, Quality::MinorSeventh).with_bass(pitch)),
+            )
+            .collect();
+
+        let parsed: Vec<_> = chords
+            .iter()
+            .map(|chord| chord.to_string())
+            .map(|string| Chord::parser::<&str>().parse(&string).unwrap().0)
+            .collect();
+
+        assert_eq!(parsed, chords);
+    }
+
+    #[test]
+    fn bass_or_root() {
+        let f_over_g = Chord::parser::<&str>().parse("F/G").unwrap().0;
+        let f = Chord::parser::<&str>().parse("F").unwrap().0;
+
+        assert_eq!(
+            f_over_g.bass_or_root(),
+            Pitch::new(Letter::G, Modifier::Natural)
+        );
+        assert_eq!(f.bass_or_root(), Pitch::new(Letter::F, Modifier::Natural));
+    }
+
+    #[test]
+    fn numeral_parser() {
+        let chords = vec![
+            ("I", "C"),
+            ("ii7", "Dm7"),
+            ("V7", "G7"),
+            ("IM7", "CM7"),
+            ("vi7", "Am7"),
+            ("iv6", "Fm6"),
+            ("ii13", "Dm13"),
+            ("vi11", "Am11"),
+            ("viiø7", "Bm7b5"),
+            ("bVII", "Bb"),
+            ("#iv°", "F#dim"),
+            ("2m7", "Dm7"),
+            ("5", "G"),
+            ("b3M7", "EbM7"),
+        ];
+
+        for (string, chord) in chords {
+            assert_eq!(
+                Chord::numeral_parser::<&str>()
+                    .parse(string)
+                    .unwrap()
+                    .0
+                    .to_string(),
+                chord,
+                "{}",
+                string
+            );
+        }
+    }
+
+    #[test]
+    fn numeral_parser_rejects_lower_case_numerals_without_a_minor_quality() {
+        for string in ["ii69", "iiM9", "ii7b9", "vi7#11", "iisus4"] {
+            assert!(
+                Chord::numeral_parser::<&str>().parse(string).is_err(),
+                "{}",
+                string
+            );
+        }
+    }
 }
@@ -0,0 +1,55 @@
+use combine::{attempt, choice, parser::char::string, Parser, Stream};
+use strum_macros::{Display, EnumIter};
+use ChordFamily::*;
+
+#[derive(Clone, Copy, Debug, Display, EnumIter, Eq, PartialEq)]
+pub enum ChordFamily {
+    #[strum(serialize = "major")]
+    Major,
+    #[strum(serialize = "minor")]
+    Minor,
+    #[strum(serialize = "dominant")]
+    Dominant,
+    #[strum(serialize = "diminished")]
+    Diminished,
+    #[strum(serialize = "half-diminished")]
+    HalfDiminished,
+    #[strum(serialize = "augmented")]
+    Augmented,
+    #[strum(serialize = "suspended")]
+    Suspended,
+}
+
+impl ChordFamily {
+    pub fn parser<Input>() -> impl Parser<Input, Output = Self>
+    where
+        Input: Stream<Token = char>,
+    {
+        choice((
+            attempt(string("major")).map(|_| Major),
+            attempt(string("minor")).map(|_| Minor),
+            attempt(string("dominant")).map(|_| Dominant),
+            attempt(string("diminished")).map(|_| Diminished),
+            attempt(string("half-diminished")).map(|_| HalfDiminished),
+            attempt(string("augmented")).map(|_| Augmented),
+            attempt(string("suspended")).map(|_| Suspended),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn parser() {
+        let parsed: Vec<_> = ChordFamily::iter()
+            .map(|family| family.to_string())
+            .map(|string| ChordFamily::parser::<&str>().parse(&string).unwrap().0)
+            .collect();
+        let families: Vec<_> = ChordFamily::iter().collect();
+
+        assert_eq!(parsed, families);
+    }
+}
@@ -27,6 +27,42 @@ impl Letter {
             token('G').map(|_| Letter::G),
         ))
     }
+
+    pub fn from_steps_from_c(steps: i8) -> Self {
+        match steps.rem_euclid(7) {
+            0 => Letter::C,
+            1 => Letter::D,
+            2 => Letter::E,
+            3 => Letter::F,
+            4 => Letter::G,
+            5 => Letter::A,
+            _ => Letter::B,
+        }
+    }
+
+    pub fn steps_from_c(&self) -> i8 {
+        match self {
+            Letter::C => 0,
+            Letter::D => 1,
+            Letter::E => 2,
+            Letter::F => 3,
+            Letter::G => 4,
+            Letter::A => 5,
+            Letter::B => 6,
+        }
+    }
+
+    pub fn semitones_from_c(&self) -> i8 {
+        match self {
+            Letter::C => 0,
+            Letter::D => 2,
+            Letter::E => 4,
+            Letter::F => 5,
+            Letter::G => 7,
+            Letter::A => 9,
+            Letter::B => 11,
+        }
+    }
 }
 
 #[cfg(test)]
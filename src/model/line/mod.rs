@@ -1,27 +1,129 @@
 use wmidi::Note;
 
-use crate::{BeatNumber, Result};
+use crate::{beat_number::TICKS_PER_SIXTEENTH_NOTE, Chord, ChordFamily, Meter, Pitch, Result};
 
+mod notation;
 mod parser;
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LinePosition {
+    pub bar: u32,
+    pub sixteenth_note: u32,
+    pub tick: u32,
+}
+
+impl LinePosition {
+    pub fn new(bar: u32, sixteenth_note: u32) -> Self {
+        Self {
+            bar,
+            sixteenth_note,
+            tick: 0,
+        }
+    }
+
+    pub fn with_tick(self, tick: u32) -> Self {
+        Self { tick, ..self }
+    }
+
+    pub fn from_ticks(ticks: u32, sixteenths_per_measure: u32) -> Self {
+        let ticks_per_measure = sixteenths_per_measure * TICKS_PER_SIXTEENTH_NOTE;
+        let ticks_this_measure = ticks % ticks_per_measure;
+        Self {
+            bar: ticks / ticks_per_measure,
+            sixteenth_note: ticks_this_measure / TICKS_PER_SIXTEENTH_NOTE,
+            tick: ticks_this_measure % TICKS_PER_SIXTEENTH_NOTE,
+        }
+    }
+
+    pub fn to_ticks(&self, sixteenths_per_measure: u32) -> u32 {
+        (self.bar * sixteenths_per_measure + self.sixteenth_note) * TICKS_PER_SIXTEENTH_NOTE
+            + self.tick
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Dynamic {
+    Normal,
+    Accent,
+    Ghost,
+    Velocity(u8),
+}
+
+const ACCENT_VELOCITY_INCREASE: u8 = 27;
+
+impl Dynamic {
+    pub fn velocity(&self, default_velocity: u8) -> u8 {
+        match self {
+            Dynamic::Normal => default_velocity,
+            Dynamic::Accent => default_velocity
+                .saturating_add(ACCENT_VELOCITY_INCREASE)
+                .min(127),
+            Dynamic::Ghost => (default_velocity / 2).max(1),
+            Dynamic::Velocity(velocity) => *velocity,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub struct LineNote {
-    pub start: BeatNumber,
-    pub duration: u32,
+    pub start: LinePosition,
+    pub duration_ticks: u32,
     pub note: Note,
+    pub dynamic: Dynamic,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Anchor {
+    Root,
+    Bass,
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub struct Line {
     pub notes: Vec<LineNote>,
+    pub fits: Vec<ChordFamily>,
+    pub anchor: Anchor,
+    pub meter: Meter,
 }
 
 impl Line {
     pub fn new(notes: Vec<LineNote>) -> Self {
-        Self { notes }
+        Self {
+            notes,
+            fits: vec![],
+            anchor: Anchor::Root,
+            meter: Meter::default(),
+        }
     }
 
-    pub fn all() -> Vec<Line> {
+    pub fn with_fits(self, fits: Vec<ChordFamily>) -> Self {
+        Self { fits, ..self }
+    }
+
+    pub fn with_anchor(self, anchor: Anchor) -> Self {
+        Self { anchor, ..self }
+    }
+
+    pub fn with_meter(self, meter: Meter) -> Self {
+        Self { meter, ..self }
+    }
+
+    pub fn anchor_pitch(&self, chord: &Chord) -> Pitch {
+        match self.anchor {
+            Anchor::Root => chord.pitch,
+            Anchor::Bass => chord.bass_or_root(),
+        }
+    }
+
+    pub fn fits(&self, chord: &Chord) -> bool {
+        self.fits.is_empty() || self.fits.contains(&chord.quality.family())
+    }
+
+    pub fn fits_measure(&self, sixteenths_per_measure: u32) -> bool {
+        self.meter.sixteenths_per_measure() == sixteenths_per_measure
+    }
+
+    pub fn built_in() -> Vec<Line> {
         vec![
             "C4 F3 G3 Bb3 C4 Db4 Eb4 F4 E4 . . .",
             "- Db4 Bb3 Db4 C4 . Bb3 G3 F3 Bb3 F3 Gb3 G3 Gb3 F3 G3 E3 . . .",
@@ -33,3 +135,57 @@ impl Line {
         .unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use combine::Parser;
+
+    #[test]
+    fn fits() {
+        let dominant_line = Line::parse("dominant: C4 Bb3").unwrap();
+        let any_line = Line::parse("C4 Bb3").unwrap();
+
+        for (chord, fits_dominant_line) in [("G7", true), ("C7b9", true), ("Dm7", false)] {
+            let chord = Chord::parser::<&str>().parse(chord).unwrap().0;
+            assert_eq!(dominant_line.fits(&chord), fits_dominant_line);
+            assert!(any_line.fits(&chord));
+        }
+    }
+
+    #[test]
+    fn anchor_pitch() {
+        let root_line = Line::parse("C4 Bb3").unwrap();
+        let bass_line = Line::parse("bass: C4 Bb3").unwrap();
+        let chord = Chord::parser::<&str>().parse("D7/F#").unwrap().0;
+
+        assert_eq!(root_line.anchor_pitch(&chord).to_string(), "D");
+        assert_eq!(bass_line.anchor_pitch(&chord).to_string(), "F#");
+    }
+
+    #[test]
+    fn line_position_ticks() {
+        assert_eq!(
+            LinePosition::from_ticks(19 * 6 + 4, 16),
+            LinePosition::new(1, 3).with_tick(4)
+        );
+        assert_eq!(
+            LinePosition::from_ticks(19 * 6, 12),
+            LinePosition::new(1, 7)
+        );
+        assert_eq!(
+            LinePosition::new(2, 5).with_tick(2).to_ticks(14),
+            33 * 6 + 2
+        );
+    }
+
+    #[test]
+    fn dynamic_velocity() {
+        assert_eq!(Dynamic::Normal.velocity(100), 100);
+        assert_eq!(Dynamic::Accent.velocity(100), 127);
+        assert_eq!(Dynamic::Accent.velocity(80), 107);
+        assert_eq!(Dynamic::Ghost.velocity(100), 50);
+        assert_eq!(Dynamic::Ghost.velocity(1), 1);
+        assert_eq!(Dynamic::Velocity(64).velocity(100), 64);
+    }
+}
@@ -0,0 +1,91 @@
+use wmidi::Note;
+
+use super::{Anchor, Dynamic, Line};
+use crate::{beat_number::TICKS_PER_SIXTEENTH_NOTE, Meter};
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+impl Line {
+    // Only lines whose notes all start and end on sixteenth notes can be
+    // written without tuplets
+    pub fn to_notation(&self) -> Option<String> {
+        let sixteenths_per_measure = self.meter.sixteenths_per_measure();
+        let mut values = vec![];
+        let mut sixteenths_so_far = 0;
+
+        for line_note in &self.notes {
+            let start = line_note.start.to_ticks(sixteenths_per_measure);
+            if start % TICKS_PER_SIXTEENTH_NOTE != 0
+                || line_note.duration_ticks % TICKS_PER_SIXTEENTH_NOTE != 0
+                || line_note.duration_ticks == 0
+            {
+                return None;
+            }
+            let start = start / TICKS_PER_SIXTEENTH_NOTE;
+            if start < sixteenths_so_far {
+                return None;
+            }
+            values.extend((sixteenths_so_far..start).map(|_| "-".to_string()));
+            values.push(note_to_notation(line_note.note, line_note.dynamic));
+            let duration = line_note.duration_ticks / TICKS_PER_SIXTEENTH_NOTE;
+            values.extend((1..duration).map(|_| ".".to_string()));
+            sixteenths_so_far = start + duration;
+        }
+
+        let mut tags: Vec<_> = self.fits.iter().map(|family| family.to_string()).collect();
+        if self.anchor == Anchor::Bass {
+            tags.push("bass".to_string());
+        }
+        if self.meter != Meter::default() {
+            tags.push(self.meter.to_string());
+        }
+
+        let body = values.join(" ");
+        Some(if tags.is_empty() {
+            body
+        } else {
+            format!("{}: {}", tags.join(", "), body)
+        })
+    }
+}
+
+fn note_to_notation(note: Note, dynamic: Dynamic) -> String {
+    let number = u8::from(note) as i32;
+    let name = format!("{}{}", NOTE_NAMES[(number % 12) as usize], number / 12 - 1);
+    match dynamic {
+        Dynamic::Normal => name,
+        Dynamic::Accent => format!(">{}", name),
+        Dynamic::Ghost => format!("({})", name),
+        Dynamic::Velocity(velocity) => format!("{}@{}", name, velocity),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Line;
+
+    #[test]
+    fn round_trips() {
+        let lines = vec![
+            "C4 F3 G3 A#3 C4 C#4 D#4 F4 E4 . . .",
+            "- C#4 A#3 . - - C#4",
+            "dominant, diminished: >C4 (D4) E4@80 .",
+            "bass, 3/4: C4 . . . E4 . . . G4 . . . F4",
+            "C-1 G9",
+        ];
+
+        for line in lines {
+            assert_eq!(
+                Line::parse(line).unwrap().to_notation().as_deref(),
+                Some(line)
+            );
+        }
+    }
+
+    #[test]
+    fn needs_sixteenth_notes() {
+        assert_eq!(Line::parse("(3 C4 D4 E4)").unwrap().to_notation(), None);
+    }
+}
@@ -1,28 +1,50 @@
-use crate::{BeatNumber, Line, LineNote, Pitch, Result};
+use super::{Anchor, Dynamic};
+use crate::{
+    beat_number::TICKS_PER_SIXTEENTH_NOTE,
+    model::{number_parser, parse_complete, separator_parser},
+    ChordFamily, Line, LineNote, LinePosition, Meter, Pitch, Result,
+};
 use combine::{
-    choice, many, many1, optional,
-    parser::char::{digit, spaces},
+    attempt, choice,
+    error::StreamError,
+    many, many1, optional,
+    parser::char::{digit, space, spaces, string},
+    sep_by1, skip_many1,
+    stream::StreamErrorFor,
     token, Parser, Stream,
 };
+use std::num::ParseIntError;
+
+const SEPARATOR_MESSAGE: &str = "notes must be separated by spaces";
 
 #[derive(Clone, Copy, Debug)]
 struct Note {
     pitch: Pitch,
     octave: i8,
     duration: u32,
+    dynamic: Dynamic,
 }
 
 #[derive(Clone, Copy, Debug)]
+enum Tag {
+    Fits(ChordFamily),
+    Anchor(Anchor),
+    Meter(Meter),
+}
+
+#[derive(Clone, Debug)]
 enum Value {
     Note(Note),
     Rest,
+    BarLine,
+    Tuplet { slot_ticks: u32, values: Vec<Value> },
 }
 
 impl Note {
     fn to_wmidi_note(self) -> wmidi::Note {
         let octave_value = self.octave + 1;
 
-        let value = octave_value * 12 + self.pitch.index();
+        let value = octave_value * 12 + self.pitch.semitones_from_c();
         wmidi::Note::from_u8_lossy(value as u8)
     }
 }
@@ -32,65 +54,208 @@ impl Line {
     where
         Input: Stream<Token = char>,
     {
-        let octave_parser = (optional(token('-')), digit()).map(|(negative, digit)| {
-            digit.to_string().parse::<i8>().unwrap() * negative.map_or(1, |_| -1)
+        let bar_line_parser = (token('|'), spaces()).map(|_| Value::BarLine);
+
+        let tuplet_parser = (
+            token('('),
+            number_parser(),
+            optional((token(':'), number_parser()).map(|(_, num_sixteenths)| num_sixteenths)),
+            skip_many1(space()),
+            many1(note_or_rest_parser()),
+            token(')'),
+            separator_parser(SEPARATOR_MESSAGE),
+        )
+            .and_then(|(_, num_slots, num_sixteenths, _, values, _, _)| {
+                to_tuplet(num_slots, num_sixteenths, values)
+                    .map_err(StreamErrorFor::<Input>::message_static_message)
+            });
+
+        let value_parser = choice((note_or_rest_parser(), bar_line_parser, tuplet_parser));
+
+        let tag_parser = choice((
+            ChordFamily::parser().map(Tag::Fits),
+            string("bass").map(|_| Tag::Anchor(Anchor::Bass)),
+            Meter::parser().map(Tag::Meter),
+        ));
+
+        let tags_parser = attempt((
+            sep_by1(tag_parser, (token(','), spaces())),
+            token(':'),
+            spaces(),
+        ))
+        .map(|(tags, _, _)| tags);
+
+        (optional(tags_parser), many1(value_parser)).map(
+            |(tags, notes): (Option<Vec<_>>, Vec<_>)| to_line(&notes, &tags.unwrap_or_default()),
+        )
+    }
+
+    pub fn parse(string: &str) -> Result<Self> {
+        parse_complete(Self::parser(), string)
+    }
+}
+
+fn note_or_rest_parser<Input>() -> impl Parser<Input, Output = Value>
+where
+    Input: Stream<Token = char>,
+{
+    let velocity_parser = (token('@'), number_parser()).and_then(|(_, velocity)| match velocity {
+        Ok(velocity) if (1..=127).contains(&velocity) => Ok(Dynamic::Velocity(velocity as u8)),
+        _ => Err(StreamErrorFor::<Input>::message_static_message(
+            "velocity must be between 1 and 127",
+        )),
+    });
+
+    let note_head_parser = choice((
+        (token('>'), pitch_octave_parser())
+            .map(|(_, (pitch, octave))| (pitch, octave, Dynamic::Accent)),
+        attempt((token('('), pitch_octave_parser(), token(')')))
+            .map(|(_, (pitch, octave), _)| (pitch, octave, Dynamic::Ghost)),
+        (pitch_octave_parser(), optional(velocity_parser))
+            .map(|((pitch, octave), dynamic)| (pitch, octave, dynamic.unwrap_or(Dynamic::Normal))),
+    ));
+
+    let dot_parser = (token('.'), separator_parser(SEPARATOR_MESSAGE)).map(|_| ());
+
+    let duration_parser = many(dot_parser).map(|dots: Vec<_>| (dots.len() + 1) as u32);
+
+    let note_parser = (
+        note_head_parser,
+        separator_parser(SEPARATOR_MESSAGE),
+        duration_parser,
+    )
+        .map(|((pitch, octave, dynamic), _, duration)| {
+            Value::Note(Note {
+                pitch,
+                octave,
+                duration,
+                dynamic,
+            })
         });
 
-        let pitch_octave_parser =
-            (Pitch::parser(), octave_parser, spaces()).map(|(pitch, octave, _)| (pitch, octave));
+    let rest_parser = (token('-'), separator_parser(SEPARATOR_MESSAGE)).map(|_| Value::Rest);
 
-        let dot_parser = (token('.'), spaces()).map(|_| ());
+    choice((note_parser, rest_parser))
+}
 
-        let duration_parser = many(dot_parser).map(|dots: Vec<_>| (dots.len() + 1) as u32);
+fn pitch_octave_parser<Input>() -> impl Parser<Input, Output = (Pitch, i8)>
+where
+    Input: Stream<Token = char>,
+{
+    let octave_parser = (optional(token('-')), digit()).map(|(negative, digit)| {
+        digit.to_string().parse::<i8>().unwrap() * negative.map_or(1, |_| -1)
+    });
 
-        let note_parser =
-            (pitch_octave_parser, duration_parser).map(|((pitch, octave), duration)| {
-                Value::Note(Note {
-                    pitch,
-                    octave,
-                    duration,
-                })
-            });
+    (Pitch::parser(), octave_parser).and_then(|(pitch, octave)| {
+        let value = (octave as i32 + 1) * 12 + pitch.semitones_from_c() as i32;
+        if (0..=127).contains(&value) {
+            Ok((pitch, octave))
+        } else {
+            Err(StreamErrorFor::<Input>::message_static_message(
+                "notes must be between C-1 and G9",
+            ))
+        }
+    })
+}
 
-        let rest_parser = (token('-'), spaces()).map(|_| Value::Rest);
+fn to_tuplet(
+    num_slots: std::result::Result<u32, ParseIntError>,
+    num_sixteenths: Option<std::result::Result<u32, ParseIntError>>,
+    values: Vec<Value>,
+) -> std::result::Result<Value, &'static str> {
+    let num_slots = num_slots.map_err(|_| "invalid tuplet size")?;
+    if num_slots < 2 {
+        return Err("a tuplet needs at least 2 notes");
+    }
+    let num_sixteenths = match num_sixteenths {
+        Some(num_sixteenths) => num_sixteenths.map_err(|_| "invalid tuplet length")?,
+        None => default_tuplet_sixteenths(num_slots),
+    };
 
-        let value_parser = choice((note_parser, rest_parser));
+    let ticks = num_sixteenths * TICKS_PER_SIXTEENTH_NOTE;
+    if !ticks.is_multiple_of(num_slots) {
+        return Err("tuplet notes must fall on the 24 PPQN clock");
+    }
 
-        many1(value_parser).map(|notes: Vec<_>| to_line(&notes))
+    let num_values_slots: u32 = values
+        .iter()
+        .map(|value| match value {
+            Value::Note(note) => note.duration,
+            _ => 1,
+        })
+        .sum();
+    if num_values_slots != num_slots {
+        return Err("tuplet doesn't contain the number of notes it's named for");
     }
 
-    pub fn parse(string: &str) -> Result<Self> {
-        let (result, _) = Self::parser::<&str>().parse(string)?;
+    Ok(Value::Tuplet {
+        slot_ticks: ticks / num_slots,
+        values,
+    })
+}
 
-        Ok(result)
+// A tuplet of n notes spans the largest power of two sixteenths below n (so a triplet takes two
+// sixteenths), except for a duplet, which spans three sixteenths like a duplet in 6/8
+fn default_tuplet_sixteenths(num_slots: u32) -> u32 {
+    if num_slots == 2 {
+        return 3;
     }
+    let mut power_of_two = 1;
+    while power_of_two * 2 < num_slots {
+        power_of_two *= 2;
+    }
+    power_of_two
 }
 
-fn to_line(notes: &[Value]) -> Line {
-    let mut line_notes = vec![];
-    let mut start = BeatNumber { sixteenth_note: 0 };
+fn to_line(values: &[Value], tags: &[Tag]) -> Line {
+    let mut line = Line::new(vec![]);
+    for tag in tags {
+        match tag {
+            Tag::Fits(family) => line.fits.push(*family),
+            Tag::Anchor(anchor) => line.anchor = *anchor,
+            Tag::Meter(meter) => line.meter = *meter,
+        }
+    }
+
+    let sixteenths_per_measure = line.meter.sixteenths_per_measure();
+    let ticks_per_measure = sixteenths_per_measure * TICKS_PER_SIXTEENTH_NOTE;
+    let mut start: u32 = 0;
 
-    for note in notes {
-        match note {
-            Value::Note(note) => {
-                let line_note = to_line_note(*note, start);
-                start = start.add_sixteenths(line_note.duration);
-                line_notes.push(line_note);
+    for value in values {
+        match value {
+            Value::BarLine => {
+                if !start.is_multiple_of(ticks_per_measure) {
+                    start += ticks_per_measure - start % ticks_per_measure;
+                }
             }
-            Value::Rest => {
-                start = start.add_sixteenths(1);
+            Value::Tuplet { slot_ticks, values } => {
+                for value in values {
+                    add_value(&mut line, &mut start, value, *slot_ticks);
+                }
             }
+            _ => add_value(&mut line, &mut start, value, TICKS_PER_SIXTEENTH_NOTE),
         }
     }
 
-    Line::new(line_notes)
+    line
 }
 
-fn to_line_note(note: Note, start: BeatNumber) -> LineNote {
-    LineNote {
-        start,
-        duration: note.duration,
-        note: note.to_wmidi_note(),
+fn add_value(line: &mut Line, start: &mut u32, value: &Value, slot_ticks: u32) {
+    match value {
+        Value::Note(note) => {
+            let line_note = LineNote {
+                start: LinePosition::from_ticks(*start, line.meter.sixteenths_per_measure()),
+                duration_ticks: note.duration * slot_ticks,
+                note: note.to_wmidi_note(),
+                dynamic: note.dynamic,
+            };
+            *start += line_note.duration_ticks;
+            line.notes.push(line_note);
+        }
+        Value::Rest => {
+            *start += slot_ticks;
+        }
+        _ => {}
     }
 }
 
@@ -98,7 +263,8 @@ fn to_line_note(note: Note, start: BeatNumber) -> LineNote {
 mod tests {
     use wmidi::Note;
 
-    use crate::{BeatNumber, Line, LineNote};
+    use super::{Anchor, Dynamic};
+    use crate::{ChordFamily, Line, LineNote, LinePosition, Meter};
 
     #[test]
     fn it_parses_line_starting_on_downbeat() {
@@ -106,49 +272,58 @@ mod tests {
             Line::parse("C4 F-1 G3 Bb3 C4 Db4 Eb4 F4 E4").unwrap(),
             Line::new(vec![
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 0 },
-                    duration: 1,
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 6,
                     note: Note::C4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 1 },
-                    duration: 1,
+                    start: LinePosition::new(0, 1),
+                    duration_ticks: 6,
                     note: Note::FMinus1,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 2 },
-                    duration: 1,
+                    start: LinePosition::new(0, 2),
+                    duration_ticks: 6,
                     note: Note::G3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 3 },
-                    duration: 1,
+                    start: LinePosition::new(0, 3),
+                    duration_ticks: 6,
                     note: Note::Bb3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 4 },
-                    duration: 1,
+                    start: LinePosition::new(0, 4),
+                    duration_ticks: 6,
                     note: Note::C4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 5 },
-                    duration: 1,
+                    start: LinePosition::new(0, 5),
+                    duration_ticks: 6,
                     note: Note::Db4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 6 },
-                    duration: 1,
+                    start: LinePosition::new(0, 6),
+                    duration_ticks: 6,
                     note: Note::Eb4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 7 },
-                    duration: 1,
+                    start: LinePosition::new(0, 7),
+                    duration_ticks: 6,
                     note: Note::F4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 8 },
-                    duration: 1,
+                    start: LinePosition::new(0, 8),
+                    duration_ticks: 6,
                     note: Note::E4,
+                    dynamic: Dynamic::Normal,
                 },
             ])
         )
@@ -160,49 +335,58 @@ mod tests {
             Line::parse("C4 F3 G3 Bb3 C4 Db4 Eb4 F4 E4 . . .").unwrap(),
             Line::new(vec![
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 0 },
-                    duration: 1,
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 6,
                     note: Note::C4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 1 },
-                    duration: 1,
+                    start: LinePosition::new(0, 1),
+                    duration_ticks: 6,
                     note: Note::F3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 2 },
-                    duration: 1,
+                    start: LinePosition::new(0, 2),
+                    duration_ticks: 6,
                     note: Note::G3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 3 },
-                    duration: 1,
+                    start: LinePosition::new(0, 3),
+                    duration_ticks: 6,
                     note: Note::Bb3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 4 },
-                    duration: 1,
+                    start: LinePosition::new(0, 4),
+                    duration_ticks: 6,
                     note: Note::C4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 5 },
-                    duration: 1,
+                    start: LinePosition::new(0, 5),
+                    duration_ticks: 6,
                     note: Note::Db4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 6 },
-                    duration: 1,
+                    start: LinePosition::new(0, 6),
+                    duration_ticks: 6,
                     note: Note::Eb4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 7 },
-                    duration: 1,
+                    start: LinePosition::new(0, 7),
+                    duration_ticks: 6,
                     note: Note::F4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 8 },
-                    duration: 4,
+                    start: LinePosition::new(0, 8),
+                    duration_ticks: 24,
                     note: Note::E4,
+                    dynamic: Dynamic::Normal,
                 },
             ])
         )
@@ -214,49 +398,58 @@ mod tests {
             Line::parse("C4 F3 G3 Bb3 C4 Db4 Eb4 F4 E4 . . . - -").unwrap(),
             Line::new(vec![
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 0 },
-                    duration: 1,
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 6,
                     note: Note::C4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 1 },
-                    duration: 1,
+                    start: LinePosition::new(0, 1),
+                    duration_ticks: 6,
                     note: Note::F3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 2 },
-                    duration: 1,
+                    start: LinePosition::new(0, 2),
+                    duration_ticks: 6,
                     note: Note::G3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 3 },
-                    duration: 1,
+                    start: LinePosition::new(0, 3),
+                    duration_ticks: 6,
                     note: Note::Bb3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 4 },
-                    duration: 1,
+                    start: LinePosition::new(0, 4),
+                    duration_ticks: 6,
                     note: Note::C4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 5 },
-                    duration: 1,
+                    start: LinePosition::new(0, 5),
+                    duration_ticks: 6,
                     note: Note::Db4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 6 },
-                    duration: 1,
+                    start: LinePosition::new(0, 6),
+                    duration_ticks: 6,
                     note: Note::Eb4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 7 },
-                    duration: 1,
+                    start: LinePosition::new(0, 7),
+                    duration_ticks: 6,
                     note: Note::F4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 8 },
-                    duration: 4,
+                    start: LinePosition::new(0, 8),
+                    duration_ticks: 24,
                     note: Note::E4,
+                    dynamic: Dynamic::Normal,
                 },
             ])
         )
@@ -268,81 +461,422 @@ mod tests {
             Line::parse("- Db4 Bb3 Db4 C4 . Bb3 G3 F3 Bb3 F3 Gb3 G3 Gb3 F3 G3 E3 . . .").unwrap(),
             Line::new(vec![
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 1 },
-                    duration: 1,
+                    start: LinePosition::new(0, 1),
+                    duration_ticks: 6,
                     note: Note::Db4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 2 },
-                    duration: 1,
+                    start: LinePosition::new(0, 2),
+                    duration_ticks: 6,
                     note: Note::Bb3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 3 },
-                    duration: 1,
+                    start: LinePosition::new(0, 3),
+                    duration_ticks: 6,
                     note: Note::Db4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 4 },
-                    duration: 2,
+                    start: LinePosition::new(0, 4),
+                    duration_ticks: 12,
                     note: Note::C4,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 6 },
-                    duration: 1,
+                    start: LinePosition::new(0, 6),
+                    duration_ticks: 6,
                     note: Note::Bb3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 7 },
-                    duration: 1,
+                    start: LinePosition::new(0, 7),
+                    duration_ticks: 6,
                     note: Note::G3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 8 },
-                    duration: 1,
+                    start: LinePosition::new(0, 8),
+                    duration_ticks: 6,
                     note: Note::F3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 9 },
-                    duration: 1,
+                    start: LinePosition::new(0, 9),
+                    duration_ticks: 6,
                     note: Note::Bb3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 10 },
-                    duration: 1,
+                    start: LinePosition::new(0, 10),
+                    duration_ticks: 6,
                     note: Note::F3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 11 },
-                    duration: 1,
+                    start: LinePosition::new(0, 11),
+                    duration_ticks: 6,
                     note: Note::Gb3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 12 },
-                    duration: 1,
+                    start: LinePosition::new(0, 12),
+                    duration_ticks: 6,
                     note: Note::G3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 13 },
-                    duration: 1,
+                    start: LinePosition::new(0, 13),
+                    duration_ticks: 6,
                     note: Note::Gb3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 14 },
-                    duration: 1,
+                    start: LinePosition::new(0, 14),
+                    duration_ticks: 6,
                     note: Note::F3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 15 },
-                    duration: 1,
+                    start: LinePosition::new(0, 15),
+                    duration_ticks: 6,
                     note: Note::G3,
+                    dynamic: Dynamic::Normal,
                 },
                 LineNote {
-                    start: BeatNumber { sixteenth_note: 0 },
-                    duration: 4,
+                    start: LinePosition::new(1, 0),
+                    duration_ticks: 24,
                     note: Note::E3,
+                    dynamic: Dynamic::Normal,
                 },
             ])
         )
     }
+
+    #[test]
+    fn it_parses_accidentals_across_octave_boundaries() {
+        assert_eq!(
+            Line::parse("F#3 Cb4 B#3 Ebb4").unwrap(),
+            Line::new(vec![
+                LineNote {
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 6,
+                    note: Note::Gb3,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 1),
+                    duration_ticks: 6,
+                    note: Note::B3,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 2),
+                    duration_ticks: 6,
+                    note: Note::C4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 3),
+                    duration_ticks: 6,
+                    note: Note::D4,
+                    dynamic: Dynamic::Normal,
+                },
+            ])
+        )
+    }
+
+    #[test]
+    fn it_parses_chord_families_the_line_fits() {
+        assert_eq!(
+            Line::parse("dominant, half-diminished: C4 . Bb3").unwrap(),
+            Line::new(vec![
+                LineNote {
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 12,
+                    note: Note::C4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 2),
+                    duration_ticks: 6,
+                    note: Note::Bb3,
+                    dynamic: Dynamic::Normal,
+                },
+            ])
+            .with_fits(vec![ChordFamily::Dominant, ChordFamily::HalfDiminished])
+        )
+    }
+
+    #[test]
+    fn it_parses_bass_anchor() {
+        assert_eq!(
+            Line::parse("bass, major: C4").unwrap(),
+            Line::new(vec![LineNote {
+                start: LinePosition::new(0, 0),
+                duration_ticks: 6,
+                note: Note::C4,
+                dynamic: Dynamic::Normal,
+            }])
+            .with_fits(vec![ChordFamily::Major])
+            .with_anchor(Anchor::Bass)
+        )
+    }
+
+    #[test]
+    fn it_parses_bar_lines() {
+        assert_eq!(
+            Line::parse("C4 . . . D4 | E4 . - F4 | | G4").unwrap(),
+            Line::new(vec![
+                LineNote {
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 24,
+                    note: Note::C4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 4),
+                    duration_ticks: 6,
+                    note: Note::D4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(1, 0),
+                    duration_ticks: 12,
+                    note: Note::E4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(1, 3),
+                    duration_ticks: 6,
+                    note: Note::F4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(2, 0),
+                    duration_ticks: 6,
+                    note: Note::G4,
+                    dynamic: Dynamic::Normal,
+                },
+            ])
+        )
+    }
+
+    #[test]
+    fn it_parses_meter() {
+        assert_eq!(
+            Line::parse("3/4: C4 . . . . . . . . . . . D4").unwrap(),
+            Line::new(vec![
+                LineNote {
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 72,
+                    note: Note::C4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(1, 0),
+                    duration_ticks: 6,
+                    note: Note::D4,
+                    dynamic: Dynamic::Normal,
+                },
+            ])
+            .with_meter(Meter::new(3, 4))
+        )
+    }
+
+    #[test]
+    fn it_parses_tuplets() {
+        assert_eq!(
+            Line::parse("(3 C4 D4 E4) F4 (3:4 G4 . A4) (6 B4 C5 - D5 E5 F5)").unwrap(),
+            Line::new(vec![
+                LineNote {
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 4,
+                    note: Note::C4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 0).with_tick(4),
+                    duration_ticks: 4,
+                    note: Note::D4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 1).with_tick(2),
+                    duration_ticks: 4,
+                    note: Note::E4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 2),
+                    duration_ticks: 6,
+                    note: Note::F4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 3),
+                    duration_ticks: 16,
+                    note: Note::G4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 5).with_tick(4),
+                    duration_ticks: 8,
+                    note: Note::A4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 7),
+                    duration_ticks: 4,
+                    note: Note::B4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 7).with_tick(4),
+                    duration_ticks: 4,
+                    note: Note::C5,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 9),
+                    duration_ticks: 4,
+                    note: Note::D5,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 9).with_tick(4),
+                    duration_ticks: 4,
+                    note: Note::E5,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 10).with_tick(2),
+                    duration_ticks: 4,
+                    note: Note::F5,
+                    dynamic: Dynamic::Normal,
+                },
+            ])
+        )
+    }
+
+    #[test]
+    fn it_parses_duplets_in_the_space_of_three_sixteenths() {
+        assert_eq!(
+            Line::parse("(2 C4 D4) E4").unwrap().notes,
+            vec![
+                LineNote {
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 9,
+                    note: Note::C4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 1).with_tick(3),
+                    duration_ticks: 9,
+                    note: Note::D4,
+                    dynamic: Dynamic::Normal,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 3),
+                    duration_ticks: 6,
+                    note: Note::E4,
+                    dynamic: Dynamic::Normal,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_rejects_tuplets_off_the_clock() {
+        assert!(Line::parse("(5 C4 D4 E4 F4 G4)").is_err());
+    }
+
+    #[test]
+    fn it_rejects_tuplets_with_wrong_number_of_notes() {
+        assert!(Line::parse("(3 C4 D4)").is_err());
+        assert!(Line::parse("(3 C4 D4 . E4)").is_err());
+    }
+
+    #[test]
+    fn it_parses_dynamics() {
+        assert_eq!(
+            Line::parse(">C4 . (D4) E4@80 . (3 >F4 (G4) A4@1)").unwrap(),
+            Line::new(vec![
+                LineNote {
+                    start: LinePosition::new(0, 0),
+                    duration_ticks: 12,
+                    note: Note::C4,
+                    dynamic: Dynamic::Accent,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 2),
+                    duration_ticks: 6,
+                    note: Note::D4,
+                    dynamic: Dynamic::Ghost,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 3),
+                    duration_ticks: 12,
+                    note: Note::E4,
+                    dynamic: Dynamic::Velocity(80),
+                },
+                LineNote {
+                    start: LinePosition::new(0, 5),
+                    duration_ticks: 4,
+                    note: Note::F4,
+                    dynamic: Dynamic::Accent,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 5).with_tick(4),
+                    duration_ticks: 4,
+                    note: Note::G4,
+                    dynamic: Dynamic::Ghost,
+                },
+                LineNote {
+                    start: LinePosition::new(0, 6).with_tick(2),
+                    duration_ticks: 4,
+                    note: Note::A4,
+                    dynamic: Dynamic::Velocity(1),
+                },
+            ])
+        )
+    }
+
+    #[test]
+    fn it_rejects_out_of_range_velocity() {
+        assert!(Line::parse("C4@128").is_err());
+        assert!(Line::parse("C4@0").is_err());
+    }
+
+    #[test]
+    fn it_rejects_out_of_range_notes() {
+        assert!(Line::parse("G9").is_ok());
+        assert!(Line::parse("Ab9").is_err());
+        assert!(Line::parse("Cb-1").is_err());
+    }
+
+    #[test]
+    fn it_reports_the_offending_token_and_column() {
+        assert_eq!(
+            Line::parse("C4 D4 X4 E4").unwrap_err().to_string(),
+            "unexpected 'X4' at column 7"
+        );
+        assert_eq!(
+            Line::parse("C4 D4@200").unwrap_err().to_string(),
+            "velocity must be between 1 and 127 at column 6 ('@200')"
+        );
+        assert_eq!(
+            Line::parse("C4D4").unwrap_err().to_string(),
+            "notes must be separated by spaces at column 3 ('D4')"
+        );
+        assert_eq!(
+            Line::parse("C4 .. D4").unwrap_err().to_string(),
+            "notes must be separated by spaces at column 5 ('.')"
+        );
+        assert_eq!(
+            Line::parse("(3 C4 D4 E4)F4").unwrap_err().to_string(),
+            "notes must be separated by spaces at column 13 ('F4')"
+        );
+    }
 }
@@ -0,0 +1,106 @@
+use super::{number_parser, parse_complete};
+use crate::Result;
+use combine::{error::StreamError, stream::StreamErrorFor, token, Parser, Stream};
+use serde::{de, Deserialize, Deserializer};
+use std::fmt;
+
+const SIXTEENTHS_PER_WHOLE_NOTE: u32 = 16;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Meter {
+    pub beats: u32,
+    pub beat_unit: u32,
+}
+
+impl Meter {
+    pub fn new(beats: u32, beat_unit: u32) -> Self {
+        Self { beats, beat_unit }
+    }
+
+    pub fn checked(beats: u32, beat_unit: u32) -> Option<Self> {
+        if beats > 0 && [1, 2, 4, 8, 16].contains(&beat_unit) {
+            Some(Self::new(beats, beat_unit))
+        } else {
+            None
+        }
+    }
+
+    pub fn sixteenths_per_measure(&self) -> u32 {
+        self.beats * SIXTEENTHS_PER_WHOLE_NOTE / self.beat_unit
+    }
+
+    pub fn parser<Input>() -> impl Parser<Input, Output = Self>
+    where
+        Input: Stream<Token = char>,
+    {
+        (number_parser(), token('/'), number_parser()).and_then(|(beats, _, beat_unit)| {
+            match (beats, beat_unit) {
+                (Ok(beats), Ok(beat_unit)) => Meter::checked(beats, beat_unit),
+                _ => None,
+            }
+            .ok_or_else(|| {
+                StreamErrorFor::<Input>::message_static_message(
+                    "time signature must look like 3/4 or 7/8",
+                )
+            })
+        })
+    }
+
+    pub fn parse(string: &str) -> Result<Self> {
+        parse_complete(Self::parser(), string)
+    }
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self::new(4, 4)
+    }
+}
+
+impl fmt::Display for Meter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format!("{}/{}", self.beats, self.beat_unit))
+    }
+}
+
+impl<'de> Deserialize<'de> for Meter {
+    fn deserialize<TDeserializer>(
+        deserializer: TDeserializer,
+    ) -> std::result::Result<Self, TDeserializer::Error>
+    where
+        TDeserializer: Deserializer<'de>,
+    {
+        let meter_string: String = Deserialize::deserialize(deserializer)?;
+        Meter::parse(&meter_string).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let meters = vec!["4/4", "3/4", "5/4", "7/8", "12/8"];
+
+        let parsed: Vec<_> = meters
+            .iter()
+            .map(|string| Meter::parse(string).unwrap().to_string())
+            .collect();
+
+        assert_eq!(parsed, meters);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_beat_unit() {
+        assert!(Meter::parse("4/3").is_err());
+        assert!(Meter::parse("0/4").is_err());
+    }
+
+    #[test]
+    fn sixteenths_per_measure() {
+        assert_eq!(Meter::new(4, 4).sixteenths_per_measure(), 16);
+        assert_eq!(Meter::new(3, 4).sixteenths_per_measure(), 12);
+        assert_eq!(Meter::new(7, 8).sixteenths_per_measure(), 14);
+    }
+}
@@ -1,7 +1,94 @@
+pub mod bar;
 pub mod chord;
+pub mod chord_family;
 pub mod letter;
 pub mod line;
+pub mod meter;
 pub mod modifier;
 pub mod pitch;
 pub mod progression;
 pub mod quality;
+
+use anyhow::anyhow;
+use combine::{
+    choice, eof, look_ahead, many1, one_of,
+    parser::char::{digit, space},
+    skip_many1,
+    stream::{easy, position, position::SourcePosition},
+    Parser, Stream,
+};
+use std::num::ParseIntError;
+
+fn number_parser<Input>() -> impl Parser<Input, Output = Result<u32, ParseIntError>>
+where
+    Input: Stream<Token = char>,
+{
+    many1(digit()).map(|digits: String| digits.parse::<u32>())
+}
+
+// Ends a chord or note, so that eg "CF" is reported instead of being read as two chords
+fn separator_parser<Input>(message: &'static str) -> impl Parser<Input, Output = ()>
+where
+    Input: Stream<Token = char>,
+{
+    choice((
+        skip_many1(space()),
+        look_ahead(one_of("|)".chars())).map(|_| ()),
+        eof(),
+    ))
+    .message(message)
+}
+
+type ParseInput<'string> = easy::Stream<position::Stream<&'string str, SourcePosition>>;
+
+fn parse_complete<'string, Output>(
+    parser: impl Parser<ParseInput<'string>, Output = Output>,
+    string: &'string str,
+) -> crate::Result<Output> {
+    let ((output, _), _) = (parser, eof())
+        .parse(easy::Stream(position::Stream::new(string)))
+        .map_err(|errors| anyhow!(describe_parse_errors(string, errors)))?;
+
+    Ok(output)
+}
+
+fn describe_parse_errors(string: &str, errors: easy::Errors<char, &str, SourcePosition>) -> String {
+    let line = string
+        .lines()
+        .nth(errors.position.line as usize - 1)
+        .unwrap_or_default();
+    let token = line
+        .chars()
+        .skip(errors.position.column as usize - 1)
+        .collect::<String>()
+        .split_whitespace()
+        .next()
+        .map(str::to_string);
+    let location = if string.contains('\n') {
+        format!(
+            "line {}, column {}",
+            errors.position.line, errors.position.column
+        )
+    } else {
+        format!("column {}", errors.position.column)
+    };
+
+    let messages: Vec<_> = errors
+        .errors
+        .iter()
+        .filter_map(|error| match error {
+            easy::Error::Message(message) => Some(message.to_string()),
+            _ => None,
+        })
+        .collect();
+    let token = match token {
+        Some(token) => format!("'{}'", token),
+        None => "end of input".to_string(),
+    };
+
+    if messages.is_empty() {
+        format!("unexpected {} at {}", token, location)
+    } else {
+        format!("{} at {} ({})", messages.join(", "), location, token)
+    }
+}
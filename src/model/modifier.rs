@@ -1,12 +1,15 @@
-use combine::{optional, token, Parser, Stream};
+use combine::{attempt, choice, optional, parser::char::string, token, Parser, Stream};
 use core::fmt;
 use strum_macros::EnumIter;
 use Modifier::*;
 
 #[derive(Clone, Copy, Debug, EnumIter, Eq, PartialEq)]
 pub enum Modifier {
+    DoubleFlat,
     Flat,
     Natural,
+    Sharp,
+    DoubleSharp,
 }
 
 impl Modifier {
@@ -14,17 +17,45 @@ impl Modifier {
     where
         Input: Stream<Token = char>,
     {
-        let flat_parser = token('b').map(|_| Flat);
+        optional(choice((
+            attempt(string("bb")).map(|_| DoubleFlat),
+            token('b').map(|_| Flat),
+            attempt(string("##")).map(|_| DoubleSharp),
+            token('#').map(|_| Sharp),
+        )))
+        .map(|modifier| modifier.unwrap_or(Natural))
+    }
+
+    pub fn from_semitones(semitones: i8) -> Option<Self> {
+        match semitones {
+            -2 => Some(DoubleFlat),
+            -1 => Some(Flat),
+            0 => Some(Natural),
+            1 => Some(Sharp),
+            2 => Some(DoubleSharp),
+            _ => None,
+        }
+    }
 
-        optional(flat_parser).map(|modifier| modifier.unwrap_or(Natural))
+    pub fn semitones(&self) -> i8 {
+        match self {
+            DoubleFlat => -2,
+            Flat => -1,
+            Natural => 0,
+            Sharp => 1,
+            DoubleSharp => 2,
+        }
     }
 }
 
 impl fmt::Display for Modifier {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let string = match self {
+            DoubleFlat => "bb",
             Flat => "b",
             Natural => "",
+            Sharp => "#",
+            DoubleSharp => "##",
         };
 
         f.write_str(string)
@@ -46,4 +77,16 @@ mod tests {
 
         assert_eq!(parsed, letters);
     }
+
+    #[test]
+    fn parser_leaves_following_input() {
+        assert_eq!(
+            Modifier::parser::<&str>().parse("bm7").unwrap(),
+            (Flat, "m7")
+        );
+        assert_eq!(
+            Modifier::parser::<&str>().parse("#4").unwrap(),
+            (Sharp, "4")
+        );
+    }
 }
@@ -1,5 +1,7 @@
-use crate::{Letter, Modifier};
+use super::parse_complete;
+use crate::{Letter, Modifier, Result};
 use combine::{Parser, Stream};
+use serde::{de, Deserialize, Deserializer};
 use std::fmt;
 use strum::IntoEnumIterator;
 
@@ -21,28 +23,36 @@ impl Pitch {
         (Letter::parser(), Modifier::parser()).map(|(letter, modifier)| Self::new(letter, modifier))
     }
 
+    pub fn parse(string: &str) -> Result<Self> {
+        parse_complete(Self::parser(), string)
+    }
+
     pub fn all() -> impl Iterator<Item = Pitch> {
         Letter::iter().flat_map(|l| Modifier::iter().map(move |m| Pitch::new(l, m)))
     }
 
-    pub fn index(&self) -> i8 {
-        match (self.letter, self.modifier) {
-            (Letter::C, Modifier::Natural) => 0,
-            (Letter::D, Modifier::Flat) => 1,
-            (Letter::D, Modifier::Natural) => 2,
-            (Letter::E, Modifier::Flat) => 3,
-            (Letter::E, Modifier::Natural) => 4,
-            (Letter::F, Modifier::Flat) => 4,
-            (Letter::F, Modifier::Natural) => 5,
-            (Letter::G, Modifier::Flat) => 6,
-            (Letter::G, Modifier::Natural) => 7,
-            (Letter::A, Modifier::Flat) => 8,
-            (Letter::A, Modifier::Natural) => 9,
-            (Letter::B, Modifier::Flat) => 10,
-            (Letter::B, Modifier::Natural) => 11,
-            (Letter::C, Modifier::Flat) => 11,
+    pub fn transposed_to(&self, key: Pitch) -> Pitch {
+        let letter =
+            Letter::from_steps_from_c(self.letter.steps_from_c() + key.letter.steps_from_c());
+        let semitones = self.semitones_from_c() + key.semitones_from_c();
+        let modifier_semitones = (semitones - letter.semitones_from_c() + 6).rem_euclid(12) - 6;
+        match Modifier::from_semitones(modifier_semitones) {
+            Some(modifier) => Pitch::new(letter, modifier),
+            // Spelling it from the key would need a triple sharp or flat
+            None => Pitch::all()
+                .filter(|pitch| pitch.index() == semitones.rem_euclid(12))
+                .min_by_key(|pitch| pitch.modifier.semitones().abs())
+                .unwrap(),
         }
     }
+
+    pub fn index(&self) -> i8 {
+        self.semitones_from_c().rem_euclid(12)
+    }
+
+    pub fn semitones_from_c(&self) -> i8 {
+        self.letter.semitones_from_c() + self.modifier.semitones()
+    }
 }
 
 impl fmt::Display for Pitch {
@@ -51,6 +61,18 @@ impl fmt::Display for Pitch {
     }
 }
 
+impl<'de> Deserialize<'de> for Pitch {
+    fn deserialize<TDeserializer>(
+        deserializer: TDeserializer,
+    ) -> std::result::Result<Self, TDeserializer::Error>
+    where
+        TDeserializer: Deserializer<'de>,
+    {
+        let pitch_string: String = Deserialize::deserialize(deserializer)?;
+        Pitch::parse(&pitch_string).map_err(de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,4 +91,31 @@ mod tests {
     fn index() {
         assert_eq!(Pitch::new(Letter::G, Modifier::Natural).index(), 7);
     }
+
+    #[test]
+    fn index_with_accidentals() {
+        assert_eq!(Pitch::new(Letter::F, Modifier::Sharp).index(), 6);
+        assert_eq!(Pitch::new(Letter::C, Modifier::Sharp).index(), 1);
+        assert_eq!(Pitch::new(Letter::B, Modifier::DoubleFlat).index(), 9);
+        assert_eq!(Pitch::new(Letter::F, Modifier::DoubleSharp).index(), 7);
+        assert_eq!(Pitch::new(Letter::C, Modifier::Flat).index(), 11);
+        assert_eq!(Pitch::new(Letter::B, Modifier::Sharp).index(), 0);
+        assert_eq!(Pitch::new(Letter::D, Modifier::DoubleFlat).index(), 0);
+    }
+
+    #[test]
+    fn transposed_to() {
+        let transposed = |pitch: &str, key: &str| {
+            Pitch::parse(pitch)
+                .unwrap()
+                .transposed_to(Pitch::parse(key).unwrap())
+                .to_string()
+        };
+
+        assert_eq!(transposed("D", "Bb"), "C");
+        assert_eq!(transposed("Bb", "F#"), "E");
+        assert_eq!(transposed("F", "Gb"), "Cb");
+        assert_eq!(transposed("F#", "C#"), "F##");
+        assert_eq!(transposed("Bbb", "Fb"), "C#");
+    }
 }
@@ -1,31 +1,194 @@
-use crate::{Chord, Result};
-use combine::{parser::char::spaces, sep_by, Parser, Stream};
+use super::{parse_complete, separator_parser};
+use crate::{Bar, Chord, Line, Meter, Pitch, Result};
+use anyhow::anyhow;
+use combine::{
+    attempt, choice,
+    error::StreamError,
+    look_ahead, many, many1, optional,
+    parser::char::{digit, spaces},
+    stream::StreamErrorFor,
+    token, Parser, Stream,
+};
 use serde::{de, Deserialize, Deserializer};
 use std::fmt;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq)]
 pub struct Progression {
-    pub chords: Vec<Chord>,
+    pub meter: Option<Meter>,
+    pub bars: Vec<Bar>,
+    in_numerals: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Item {
+    Chord(Chord),
+    Numeral(Chord),
+    Repeat,
+    BarLine,
 }
 
 impl Progression {
     pub fn new(chords: &[Chord]) -> Self {
+        Self::from_bars(chords.iter().map(|chord| Bar::new(vec![*chord])).collect())
+    }
+
+    pub fn from_bars(bars: Vec<Bar>) -> Self {
         Self {
-            chords: chords.to_vec(),
+            meter: None,
+            bars,
+            in_numerals: false,
         }
     }
 
+    pub fn with_meter(self, meter: Meter) -> Self {
+        Self {
+            meter: Some(meter),
+            ..self
+        }
+    }
+
+    pub fn in_key(self, key: Pitch) -> Result<Self> {
+        if !self.in_numerals {
+            return Err(anyhow!(
+                "a key can only be given for progressions written in numerals, like ii7 V7 IM7"
+            ));
+        }
+
+        Ok(Self {
+            bars: self
+                .bars
+                .iter()
+                .map(|bar| {
+                    Bar::new(
+                        bar.chords
+                            .iter()
+                            .map(|chord| chord.transposed_to(key))
+                            .collect(),
+                    )
+                })
+                .collect(),
+            in_numerals: false,
+            ..self
+        })
+    }
+
+    pub fn chords_without_lines(&self, lines: &[Line], meter: Meter) -> Vec<Chord> {
+        let mut chords: Vec<Chord> = vec![];
+        for chord in self.bars.iter().flat_map(|bar| &bar.chords) {
+            let has_line = lines
+                .iter()
+                .any(|line| line.fits(chord) && line.fits_measure(meter.sixteenths_per_measure()));
+            if !has_line && !chords.contains(chord) {
+                chords.push(*chord);
+            }
+        }
+        chords
+    }
+
+    pub fn chord_transposing_out_of_range(&self, line: &Line) -> Option<Chord> {
+        let highest_note = line
+            .notes
+            .iter()
+            .map(|line_note| u8::from(line_note.note))
+            .max()?;
+        self.bars
+            .iter()
+            .flat_map(|bar| &bar.chords)
+            .filter(|chord| line.fits(chord))
+            .find(|chord| u32::from(highest_note) + line.anchor_pitch(chord).index() as u32 > 127)
+            .copied()
+    }
+
     pub fn parser<Input>() -> impl Parser<Input, Output = Self>
     where
         Input: Stream<Token = char>,
     {
-        sep_by(Chord::parser(), spaces()).map(|chords: Vec<_>| Self::new(&chords))
+        let item_parser = choice((
+            (
+                choice((
+                    Chord::parser().map(Item::Chord),
+                    Chord::numeral_parser().map(Item::Numeral),
+                    token('.').map(|_| Item::Repeat),
+                )),
+                separator_parser("chords must be separated by spaces"),
+            )
+                .map(|(item, _)| item),
+            (token('|'), spaces()).map(|_| Item::BarLine),
+        ));
+
+        let meter_parser = (
+            attempt(look_ahead((many1::<String, _, _>(digit()), token('/')))),
+            Meter::parser(),
+            spaces(),
+        )
+            .map(|(_, meter, _)| meter);
+
+        (spaces(), optional(meter_parser), many(item_parser)).and_then(
+            |(_, meter, items): (_, _, Vec<_>)| {
+                let progression = to_progression(&items)
+                    .map_err(StreamErrorFor::<Input>::message_static_message)?;
+                Ok::<_, StreamErrorFor<Input>>(match meter {
+                    Some(meter) => progression.with_meter(meter),
+                    None => progression,
+                })
+            },
+        )
     }
 
     pub fn parse(string: &str) -> Result<Self> {
-        let (result, _) = Self::parser::<&str>().parse(string)?;
+        parse_complete(Self::parser(), string)
+    }
+}
 
-        Ok(result)
+fn to_progression(items: &[Item]) -> std::result::Result<Progression, &'static str> {
+    let has_bar_lines = items.iter().any(|item| matches!(item, Item::BarLine));
+    let in_numerals = items.iter().any(|item| matches!(item, Item::Numeral(_)));
+    if in_numerals && items.iter().any(|item| matches!(item, Item::Chord(_))) {
+        return Err("progression can't mix chord names and numerals");
+    }
+
+    let mut bars = vec![];
+    let mut current_bar = vec![];
+    let mut previous_chord = None;
+
+    for item in items {
+        match item {
+            Item::Chord(chord) | Item::Numeral(chord) => {
+                current_bar.push(*chord);
+                previous_chord = Some(*chord);
+            }
+            Item::Repeat => {
+                current_bar.push(previous_chord.ok_or("'.' must follow a chord")?);
+            }
+            Item::BarLine => {}
+        }
+        let is_end_of_bar = match item {
+            Item::BarLine => true,
+            _ => !has_bar_lines,
+        };
+        if is_end_of_bar && !current_bar.is_empty() {
+            bars.push(Bar::new(current_bar));
+            current_bar = vec![];
+        }
+    }
+    if !current_bar.is_empty() {
+        bars.push(Bar::new(current_bar));
+    }
+
+    if bars.is_empty() {
+        return Err("progression must contain at least one chord");
+    }
+
+    Ok(Progression {
+        in_numerals,
+        ..Progression::from_bars(bars)
+    })
+}
+
+// Whether the chords were written as numerals doesn't change what's played
+impl PartialEq for Progression {
+    fn eq(&self, other: &Self) -> bool {
+        self.meter == other.meter && self.bars == other.bars
     }
 }
 
@@ -37,13 +200,20 @@ impl Default for Progression {
 
 impl fmt::Display for Progression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let string = self
-            .chords
+        let bars = self
+            .bars
             .iter()
-            .map(|chord| chord.to_string())
-            .collect::<Vec<_>>()
-            .join(" ");
-        f.write_str(&string)
+            .map(|bar| bar.to_string())
+            .collect::<Vec<_>>();
+        let string = if self.bars.iter().all(|bar| bar.chords.len() == 1) {
+            bars.join(" ")
+        } else {
+            format!("| {} |", bars.join(" | "))
+        };
+        match self.meter {
+            Some(meter) => f.write_str(&format!("{} {}", meter, string)),
+            None => f.write_str(&string),
+        }
     }
 }
 
@@ -65,7 +235,16 @@ mod tests {
 
     #[test]
     fn parse() {
-        let progressions = vec!["A Bm CM7 D7 Em7".to_string()];
+        let progressions = vec![
+            "A Bm CM7 D7 Em7".to_string(),
+            "E C#m7 F#m7 B7".to_string(),
+            "Bbb Fb C##m7 G#".to_string(),
+            "C/E F/G Am7/G D7/F#".to_string(),
+            "| Dm7 G7 | CM7 . . . |".to_string(),
+            "| C . . A7 | Dm7 G7 | C |".to_string(),
+            "3/4 | C . G7 | C |".to_string(),
+            "7/8 Dm7 G7".to_string(),
+        ];
 
         let parsed: Vec<_> = progressions
             .iter()
@@ -75,4 +254,166 @@ mod tests {
 
         assert_eq!(parsed, progressions)
     }
+
+    #[test]
+    fn parse_bars() {
+        let progression = Progression::parse("|Dm7 G7| CM7 . . . ||").unwrap();
+
+        assert_eq!(progression.bars.len(), 2);
+        assert_eq!(progression.bars[0].chords.len(), 2);
+        assert_eq!(progression.bars[1].chords.len(), 4);
+        assert_eq!(progression.bars[1].chords[3].to_string(), "CM7");
+    }
+
+    #[test]
+    fn parse_meter() {
+        assert_eq!(
+            Progression::parse("3/4 C F").unwrap().meter,
+            Some(Meter::new(3, 4))
+        );
+        assert_eq!(Progression::parse("C F").unwrap().meter, None);
+    }
+
+    #[test]
+    fn parse_repeat_without_bar_lines() {
+        assert_eq!(
+            Progression::parse("C . F").unwrap(),
+            Progression::parse("C C F").unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_rejects_leading_repeat() {
+        assert!(Progression::parse("| . C |").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_empty_progression() {
+        assert!(Progression::parse("| |").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_trailing_garbage() {
+        assert_eq!(
+            Progression::parse("| Dm7 G7 | CM7 | %")
+                .unwrap_err()
+                .to_string(),
+            "unexpected '%' at column 18"
+        );
+        assert_eq!(
+            Progression::parse("C F Xm7 G").unwrap_err().to_string(),
+            "unexpected 'Xm7' at column 5"
+        );
+    }
+
+    #[test]
+    fn parse_requires_spaces_between_chords() {
+        assert_eq!(
+            Progression::parse("Dm7G7").unwrap_err().to_string(),
+            "chords must be separated by spaces at column 4 ('G7')"
+        );
+        assert_eq!(
+            Progression::parse("C F CF").unwrap_err().to_string(),
+            "chords must be separated by spaces at column 6 ('F')"
+        );
+        assert_eq!(
+            Progression::parse("ii7V7").unwrap_err().to_string(),
+            "chords must be separated by spaces at column 4 ('V7')"
+        );
+    }
+
+    #[test]
+    fn parse_altered_dominants() {
+        assert_eq!(
+            Progression::parse("C7b5 G7b13").unwrap().to_string(),
+            "C7b5 G7b13"
+        );
+        assert_eq!(
+            Progression::parse("C7b6").unwrap_err().to_string(),
+            "chords must be separated by spaces at column 3 ('b6')"
+        );
+    }
+
+    #[test]
+    fn parse_reports_invalid_meter() {
+        assert_eq!(
+            Progression::parse("3/5 C").unwrap_err().to_string(),
+            "time signature must look like 3/4 or 7/8 at column 1 ('3/5')"
+        );
+    }
+
+    #[test]
+    fn parse_numerals_in_key() {
+        let key = Pitch::parse("Bb").unwrap();
+
+        assert_eq!(
+            Progression::parse("ii7 V7 IM7 vi7")
+                .unwrap()
+                .in_key(key)
+                .unwrap()
+                .to_string(),
+            "Cm7 F7 BbM7 Gm7"
+        );
+        assert_eq!(
+            Progression::parse("3/4 | 2m7 5 | 1 . 6m |")
+                .unwrap()
+                .in_key(key)
+                .unwrap()
+                .to_string(),
+            "3/4 | Cm7 F | Bb . Gm |"
+        );
+    }
+
+    #[test]
+    fn parse_numerals_default_to_c() {
+        assert_eq!(
+            Progression::parse("ii7 V7 bVIIM7").unwrap().to_string(),
+            "Dm7 G7 BbM7"
+        );
+    }
+
+    #[test]
+    fn numerals_equal_the_chords_they_resolve_to() {
+        assert_eq!(
+            Progression::parse("ii V I").unwrap(),
+            Progression::parse("Dm G C").unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_rejects_mixed_chords_and_numerals() {
+        assert!(Progression::parse("Dm7 V7 I").is_err());
+    }
+
+    #[test]
+    fn in_key_rejects_chord_names() {
+        assert!(Progression::parse("Dm7 G7")
+            .unwrap()
+            .in_key(Pitch::parse("Bb").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn chords_without_lines() {
+        let progression = Progression::parse("| Dm7 G7 | CM7 | G7 |").unwrap();
+        let lines = vec![
+            Line::parse("dominant: C4").unwrap(),
+            Line::parse("3/4: C4").unwrap(),
+        ];
+
+        assert_eq!(
+            progression
+                .chords_without_lines(&lines, Meter::new(4, 4))
+                .iter()
+                .map(|chord| chord.to_string())
+                .collect::<Vec<_>>(),
+            vec!["Dm7", "CM7"]
+        );
+        assert_eq!(
+            progression
+                .chords_without_lines(&lines, Meter::new(3, 4))
+                .len(),
+            0
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use crate::ChordFamily;
 use combine::{attempt, choice, optional, parser::char::string, Parser, Stream};
 use strum_macros::{Display, EnumIter};
 use Quality::*;
@@ -8,27 +9,160 @@ pub enum Quality {
     Major,
     #[strum(serialize = "m")]
     Minor,
+    #[strum(serialize = "6")]
+    Sixth,
+    #[strum(serialize = "m6")]
+    MinorSixth,
+    #[strum(serialize = "69")]
+    SixNine,
     #[strum(serialize = "M7")]
     MajorSeventh,
     #[strum(serialize = "7")]
     Seventh,
     #[strum(serialize = "m7")]
     MinorSeventh,
+    #[strum(serialize = "mM7")]
+    MinorMajorSeventh,
+    #[strum(serialize = "M9")]
+    MajorNinth,
+    #[strum(serialize = "9")]
+    Ninth,
+    #[strum(serialize = "m9")]
+    MinorNinth,
+    #[strum(serialize = "11")]
+    Eleventh,
+    #[strum(serialize = "m11")]
+    MinorEleventh,
+    #[strum(serialize = "13")]
+    Thirteenth,
+    #[strum(serialize = "m13")]
+    MinorThirteenth,
+    #[strum(serialize = "7b9")]
+    SeventhFlatNine,
+    #[strum(serialize = "7#9")]
+    SeventhSharpNine,
+    #[strum(serialize = "7#11")]
+    SeventhSharpEleven,
+    #[strum(serialize = "7b5")]
+    SeventhFlatFive,
+    #[strum(serialize = "7b13")]
+    SeventhFlatThirteen,
+    #[strum(serialize = "7alt")]
+    Altered,
+    #[strum(serialize = "sus2")]
+    SuspendedSecond,
+    #[strum(serialize = "sus4")]
+    SuspendedFourth,
+    #[strum(serialize = "7sus4")]
+    SeventhSuspendedFourth,
+    #[strum(serialize = "dim")]
+    Diminished,
+    #[strum(serialize = "dim7")]
+    DiminishedSeventh,
+    #[strum(serialize = "m7b5")]
+    HalfDiminished,
+    #[strum(serialize = "aug")]
+    Augmented,
+    #[strum(serialize = "7#5")]
+    AugmentedSeventh,
 }
 
+// Symbols are tried in order, so within each list (and across the two lists)
+// longer symbols must come before any symbol that is a prefix of them. The
+// split into two lists is only there to stay within the array sizes `choice()`
+// supports.
+const LONG_SYMBOLS: [(&str, Quality); 24] = [
+    ("7sus4", SeventhSuspendedFourth),
+    ("m7b5", HalfDiminished),
+    ("dim7", DiminishedSeventh),
+    ("sus2", SuspendedSecond),
+    ("sus4", SuspendedFourth),
+    ("7sus", SeventhSuspendedFourth),
+    ("7#11", SeventhSharpEleven),
+    ("7b13", SeventhFlatThirteen),
+    ("7alt", Altered),
+    ("maj7", MajorSeventh),
+    ("maj9", MajorNinth),
+    ("min7", MinorSeventh),
+    ("mM7", MinorMajorSeventh),
+    ("m11", MinorEleventh),
+    ("m13", MinorThirteenth),
+    ("7b9", SeventhFlatNine),
+    ("7#9", SeventhSharpNine),
+    ("7#5", AugmentedSeventh),
+    ("7b5", SeventhFlatFive),
+    ("maj", Major),
+    ("dim", Diminished),
+    ("aug", Augmented),
+    ("sus", SuspendedFourth),
+    ("min", Minor),
+];
+
+const SHORT_SYMBOLS: [(&str, Quality); 22] = [
+    ("m6", MinorSixth),
+    ("69", SixNine),
+    ("M7", MajorSeventh),
+    ("m7", MinorSeventh),
+    ("M9", MajorNinth),
+    ("m9", MinorNinth),
+    ("11", Eleventh),
+    ("13", Thirteenth),
+    ("Δ7", MajorSeventh),
+    ("-7", MinorSeventh),
+    ("ø7", HalfDiminished),
+    ("°7", DiminishedSeventh),
+    ("m", Minor),
+    ("M", Major),
+    ("Δ", MajorSeventh),
+    ("6", Sixth),
+    ("7", Seventh),
+    ("9", Ninth),
+    ("-", Minor),
+    ("ø", HalfDiminished),
+    ("°", Diminished),
+    ("+", Augmented),
+];
+
 impl Quality {
     pub fn parser<Input>() -> impl Parser<Input, Output = Self>
     where
         Input: Stream<Token = char>,
     {
         optional(choice((
-            string("M7").map(|_| MajorSeventh),
-            string("7").map(|_| Seventh),
-            attempt(string("m7").map(|_| MinorSeventh)),
-            string("m").map(|_| Minor),
+            choice(
+                LONG_SYMBOLS.map(|(symbol, quality)| attempt(string(symbol)).map(move |_| quality)),
+            ),
+            choice(
+                SHORT_SYMBOLS
+                    .map(|(symbol, quality)| attempt(string(symbol)).map(move |_| quality)),
+            ),
         )))
         .map(|quality| quality.unwrap_or(Major))
     }
+
+    pub fn family(&self) -> ChordFamily {
+        match self {
+            Major | Sixth | SixNine | MajorSeventh | MajorNinth => ChordFamily::Major,
+            Minor | MinorSixth | MinorSeventh | MinorMajorSeventh | MinorNinth | MinorEleventh
+            | MinorThirteenth => ChordFamily::Minor,
+            Seventh
+            | Ninth
+            | Eleventh
+            | Thirteenth
+            | SeventhFlatNine
+            | SeventhSharpNine
+            | SeventhSharpEleven
+            | SeventhFlatFive
+            | SeventhFlatThirteen
+            | Altered
+            | SeventhSuspendedFourth
+            | AugmentedSeventh => ChordFamily::Dominant,
+            SuspendedSecond | SuspendedFourth => ChordFamily::Suspended,
+            Diminished | DiminishedSeventh => ChordFamily::Diminished,
+            HalfDiminished => ChordFamily::HalfDiminished,
+            Augmented => ChordFamily::Augmented,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -46,4 +180,64 @@ mod test {
 
         assert_eq!(parsed, qualities);
     }
+
+    #[test]
+    fn parser_aliases() {
+        let aliases = vec![
+            ("maj7", MajorSeventh),
+            ("Δ7", MajorSeventh),
+            ("-7", MinorSeventh),
+            ("min7", MinorSeventh),
+            ("-", Minor),
+            ("min", Minor),
+            ("ø", HalfDiminished),
+            ("ø7", HalfDiminished),
+            ("°", Diminished),
+            ("°7", DiminishedSeventh),
+            ("+", Augmented),
+            ("sus", SuspendedFourth),
+            ("7sus", SeventhSuspendedFourth),
+            ("maj9", MajorNinth),
+            ("maj", Major),
+            ("M", Major),
+            ("Δ", MajorSeventh),
+            ("m13", MinorThirteenth),
+            ("11", Eleventh),
+        ];
+
+        for (alias, quality) in aliases {
+            assert_eq!(
+                Quality::parser::<&str>().parse(alias).unwrap(),
+                (quality, ""),
+                "{}",
+                alias
+            );
+        }
+    }
+
+    #[test]
+    fn symbols_are_matched_longest_first() {
+        for symbols in [&LONG_SYMBOLS[..], &SHORT_SYMBOLS[..]] {
+            for (index, (symbol, _)) in symbols.iter().enumerate() {
+                for (later_symbol, _) in &symbols[index + 1..] {
+                    assert!(
+                        !later_symbol.starts_with(symbol),
+                        "'{}' shadows '{}'",
+                        symbol,
+                        later_symbol
+                    );
+                }
+            }
+        }
+        for (long_symbol, _) in &LONG_SYMBOLS {
+            for (short_symbol, _) in &SHORT_SYMBOLS {
+                assert!(
+                    !short_symbol.starts_with(long_symbol),
+                    "'{}' shadows '{}'",
+                    long_symbol,
+                    short_symbol
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,141 @@
+use midly::{
+    live::LiveEvent,
+    num::{u15, u24, u28},
+    Format, Header, MetaMessage, Smf, Timing, TrackEvent, TrackEventKind,
+};
+use std::path::Path;
+
+use crate::{
+    beat_number::{TICKS_PER_QUARTER_NOTE, TICKS_PER_SIXTEENTH_NOTE},
+    LineLauncher, Meter, Result,
+};
+
+pub fn render(
+    line_launcher: &LineLauncher,
+    meter: Meter,
+    bpm: f64,
+    choruses: u32,
+) -> Result<Smf<'static>> {
+    let num_ticks = choruses
+        * line_launcher.progression.bars.len() as u32
+        * meter.sixteenths_per_measure()
+        * TICKS_PER_SIXTEENTH_NOTE;
+
+    let mut track = vec![
+        TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new(
+                (60_000_000.0 / bpm).round() as u32
+            ))),
+        },
+        TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::TimeSignature(
+                meter.beats as u8,
+                meter.beat_unit.trailing_zeros() as u8,
+                TICKS_PER_QUARTER_NOTE as u8,
+                8,
+            )),
+        },
+    ];
+
+    let mut last_tick = 0;
+    for (tick, midi_message) in line_launcher.render(meter, num_ticks) {
+        let mut bytes_buffer = vec![0; midi_message.bytes_size()];
+        midi_message.copy_to_slice(&mut bytes_buffer)?;
+        if let LiveEvent::Midi { channel, message } = LiveEvent::parse(&bytes_buffer)? {
+            track.push(TrackEvent {
+                delta: u28::new(tick - last_tick),
+                kind: TrackEventKind::Midi { channel, message },
+            });
+            last_tick = tick;
+        }
+    }
+    track.push(TrackEvent {
+        delta: u28::new(num_ticks - last_tick),
+        kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+    });
+
+    let mut smf = Smf::new(Header::new(
+        Format::SingleTrack,
+        Timing::Metrical(u15::new(TICKS_PER_QUARTER_NOTE as u16)),
+    ));
+    smf.tracks.push(track);
+
+    Ok(smf)
+}
+
+pub fn render_to_file(
+    line_launcher: &LineLauncher,
+    meter: Meter,
+    bpm: f64,
+    choruses: u32,
+    path: &Path,
+) -> Result<()> {
+    render(line_launcher, meter, bpm, choruses)?.save(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Line, Progression};
+    use midly::{num::u7, MidiMessage};
+
+    #[test]
+    fn renders_tempo_time_signature_and_notes() {
+        let line_launcher = LineLauncher::from(
+            Progression::parse("C F").unwrap(),
+            vec![Line::parse("3/4: C4 D4 . E4").unwrap()],
+        );
+
+        let smf = render(&line_launcher, Meter::new(3, 4), 125.0, 2).unwrap();
+
+        assert_eq!(smf.header.format, Format::SingleTrack);
+        assert_eq!(smf.header.timing, Timing::Metrical(u15::new(24)));
+        let track = &smf.tracks[0];
+        assert_eq!(
+            track[0].kind,
+            TrackEventKind::Meta(MetaMessage::Tempo(u24::new(480_000)))
+        );
+        assert_eq!(
+            track[1].kind,
+            TrackEventKind::Meta(MetaMessage::TimeSignature(3, 2, 24, 8))
+        );
+        assert_eq!(
+            track[2].kind,
+            TrackEventKind::Midi {
+                channel: 0.into(),
+                message: MidiMessage::NoteOn {
+                    key: u7::new(60),
+                    vel: u7::new(100)
+                }
+            }
+        );
+        assert_eq!(track.len(), 2 + 4 * 6 + 1);
+        let total_ticks: u32 = track.iter().map(|event| event.delta.as_int()).sum();
+        assert_eq!(total_ticks, 4 * 12 * 6);
+        assert_eq!(
+            track.last().unwrap().kind,
+            TrackEventKind::Meta(MetaMessage::EndOfTrack)
+        );
+    }
+
+    #[test]
+    fn renders_a_readable_file() {
+        let line_launcher = LineLauncher::from(
+            Progression::parse("C").unwrap(),
+            vec![Line::parse("C4 D4").unwrap()],
+        );
+        let mut bytes = vec![];
+        render(&line_launcher, Meter::new(4, 4), 120.0, 1)
+            .unwrap()
+            .write_std(&mut bytes)
+            .unwrap();
+
+        let smf = Smf::parse(&bytes).unwrap();
+
+        assert_eq!(smf.tracks.len(), 1);
+        assert_eq!(smf.tracks[0].len(), 2 + 4 + 1);
+    }
+}
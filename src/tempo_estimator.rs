@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+
+use crate::beat_number::TICKS_PER_SIXTEENTH_NOTE;
+
+const SMOOTHING: f64 = 0.1;
+const MAX_TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TempoEstimator {
+    last_tick: Option<Instant>,
+    tick_seconds: Option<f64>,
+}
+
+impl TempoEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        self.last_tick = None;
+    }
+
+    pub fn tick(&mut self, at: Instant) {
+        if let Some(last_tick) = self.last_tick {
+            let interval = at.saturating_duration_since(last_tick);
+            // Anything this long is a gap in the clock (eg a pause) rather than a tempo
+            if interval <= MAX_TICK_INTERVAL {
+                let interval = interval.as_secs_f64();
+                self.tick_seconds = Some(match self.tick_seconds {
+                    Some(tick_seconds) => tick_seconds + SMOOTHING * (interval - tick_seconds),
+                    None => interval,
+                });
+            }
+        }
+        self.last_tick = Some(at);
+    }
+
+    pub fn tick_duration(&self) -> Option<Duration> {
+        self.tick_seconds.map(Duration::from_secs_f64)
+    }
+
+    pub fn sixteenth_note_duration(&self) -> Option<Duration> {
+        self.tick_duration()
+            .map(|tick_duration| tick_duration * TICKS_PER_SIXTEENTH_NOTE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticked(intervals_ms: &[u64]) -> TempoEstimator {
+        let mut tempo_estimator = TempoEstimator::new();
+        let mut now = Instant::now();
+        tempo_estimator.tick(now);
+        for interval in intervals_ms {
+            now += Duration::from_millis(*interval);
+            tempo_estimator.tick(now);
+        }
+        tempo_estimator
+    }
+
+    fn tick_ms(tempo_estimator: &TempoEstimator) -> f64 {
+        tempo_estimator.tick_duration().unwrap().as_secs_f64() * 1000.0
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn needs_two_ticks() {
+        assert_eq!(ticked(&[]).tick_duration(), None);
+        assert_eq!(ticked(&[]).sixteenth_note_duration(), None);
+    }
+
+    #[test]
+    fn steady_clock() {
+        let tempo_estimator = ticked(&[20; 48]);
+
+        assert_close(tick_ms(&tempo_estimator), 20.0);
+        assert_close(
+            tempo_estimator
+                .sixteenth_note_duration()
+                .unwrap()
+                .as_secs_f64(),
+            0.12,
+        );
+    }
+
+    #[test]
+    fn smooths_a_late_tick() {
+        let mut intervals = vec![20; 48];
+        intervals.push(40);
+        intervals.push(0);
+        let tempo_estimator = ticked(&intervals);
+
+        assert!((tick_ms(&tempo_estimator) - 20.0).abs() < 20.0 * 0.1);
+    }
+
+    #[test]
+    fn follows_tempo_changes() {
+        let mut intervals = vec![20; 48];
+        intervals.extend(vec![25; 96]);
+
+        assert_close(tick_ms(&ticked(&intervals)), 25.0);
+    }
+
+    #[test]
+    fn ignores_gaps() {
+        let mut intervals = vec![20; 48];
+        intervals.push(5000);
+        intervals.extend(vec![20; 2]);
+
+        assert_close(tick_ms(&ticked(&intervals)), 20.0);
+    }
+}